@@ -1,7 +1,7 @@
 // Vertex shader
 
 struct Uniforms {
-    aspect_ratio: f32,
+    view_proj: mat4x4<f32>,
 };
 
 @group(1) @binding(0)
@@ -15,11 +15,15 @@ struct VertexInput {
 struct InstanceInput {
     @location(2) instance_pos: vec3<f32>,
     @location(3) theta: f32,
+    @location(4) scale: vec2<f32>,
+    @location(5) tint: vec4<f32>,
+    @location(6) opacity: f32,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) tint: vec4<f32>,
 }   
 
 @vertex
@@ -29,25 +33,17 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
+    out.tint = vec4<f32>(instance.tint.rgb, instance.tint.a * instance.opacity);
 
     let rotation_matrix = mat2x2<f32>(
         cos(instance.theta), -sin(instance.theta),
         sin(instance.theta),  cos(instance.theta)
     );
 
-    let rotated_position = rotation_matrix * model.position.xy;
-    let aspect_ratio_corrected_position = vec2<f32>(
-        rotated_position.x / u_uniforms.aspect_ratio,
-        rotated_position.y
-    );
+    let rotated_position = rotation_matrix * (model.position.xy * instance.scale);
+    let world_position = vec3<f32>(rotated_position, model.position.z) + instance.instance_pos;
 
-    let scaled_instance_position = vec3<f32>(
-        instance.instance_pos.x / u_uniforms.aspect_ratio,
-        instance.instance_pos.y,
-        instance.instance_pos.z
-    );
-    
-    out.clip_position = vec4<f32>(vec3<f32>(aspect_ratio_corrected_position, model.position.z) + scaled_instance_position, 1.0);
+    out.clip_position = u_uniforms.view_proj * vec4<f32>(world_position, 1.0);
 
     // out.clip_position = vec4<f32>(model.position, 1.0);
 
@@ -63,5 +59,5 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.tint;
 }
\ No newline at end of file
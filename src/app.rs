@@ -1,15 +1,34 @@
+use crate::batch::SpriteBatcher;
+use crate::camera::Camera;
+use crate::config::Config;
+use crate::constants::{VIEW_HEIGHT, VIEW_WIDTH};
 use crate::game::Game;
-use crate::game_object::{Enemy, Gate, Player};
-use crate::sprite::{Instance, Sprite, Uniforms, Vertex};
+use crate::gpu_buffer::GrowableBuffer;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::hot_reload::{HotReload, Reload};
+use crate::mesh::MeshRegistry;
+use crate::mode::GameMode;
+use crate::options::Options;
+use crate::replay::Recorder;
+use crate::sprite::{Instance, Uniforms, Vertex};
+use crate::state::GameState;
 use crate::texture::Texture;
+use crate::waves::WaveSchedule;
 
+use std::fs::File;
+use std::io::BufWriter;
 use std::iter;
+use std::path::PathBuf;
 use wgpu::util::DeviceExt;
+#[cfg(not(target_arch = "wasm32"))]
+use winit::dpi::LogicalSize;
 use winit::{
     event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
     window::Window,
 };
 
+const ZOOM_STEP: f32 = 1.25;
+
 pub struct App {
     pub game: Game,
 
@@ -18,22 +37,50 @@ pub struct App {
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
+    camera: Camera,
+    render_pipeline_layout: wgpu::PipelineLayout,
     render_pipeline: wgpu::RenderPipeline,
 
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
-    instance_buffer: wgpu::Buffer,
+    meshes: MeshRegistry,
+    batcher: SpriteBatcher,
+    instance_buffer: GrowableBuffer,
     uniform_buffer: wgpu::Buffer,
 
     bind_group: wgpu::BindGroup,
     uniform_bind_group: wgpu::BindGroup,
 
+    record_dir: Option<PathBuf>,
+    recorder: Option<Recorder<BufWriter<File>>>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    hot_reload: Option<HotReload>,
+    /// A config edit picked up mid-recording, held back until it ends.
+    #[cfg(not(target_arch = "wasm32"))]
+    pending_config: Option<Config>,
+
     window: Window,
 }
 
 impl App {
-    pub async fn new(window: Window) -> Self {
-        let game = Game::new();
+    pub async fn new(window: Window, config: Config, options: &Options) -> Self {
+        let mut mode = match options.mode.as_deref().map(GameMode::by_name) {
+            Some(Some(mode)) => mode,
+            Some(None) => {
+                log::warn!("unknown mode, playing classic");
+                GameMode::default()
+            }
+            None => GameMode::default(),
+        };
+        if let Some(path) = &options.waves {
+            match WaveSchedule::load(path) {
+                Ok(waves) => mode.waves = waves,
+                Err(e) => log::error!("couldn't load waves from {}: {}", path.display(), e),
+            }
+        }
+        let game = match options.seed {
+            Some(seed) => Game::with_config(config, mode, seed),
+            None => Game::with_random_seed(config, mode),
+        };
 
         let size = window.inner_size();
         // The instance is a handle to our GPU
@@ -128,6 +175,10 @@ impl App {
             include_bytes!("../assets/circle.png").as_ref(),
             include_bytes!("../assets/diamond.png").as_ref(),
             include_bytes!("../assets/triangle.png").as_ref(),
+            include_bytes!("../assets/wanderer.png").as_ref(),
+            include_bytes!("../assets/charger.png").as_ref(),
+            include_bytes!("../assets/splitter.png").as_ref(),
+            include_bytes!("../assets/gate_avoider.png").as_ref(),
         ];
         let texture_atlas =
             Texture::create_atlas(&device, &queue, atlas_bytes, Some("atlas")).unwrap();
@@ -152,12 +203,11 @@ impl App {
             source: wgpu::ShaderSource::Wgsl(include_str!("../assets/shader.wgsl").into()),
         });
 
-        // Create the uniform buffer
-        let aspect_ratio = size.width as f32 / size.height as f32;
-        println!("aspect_ratio: {}", aspect_ratio);
+        let camera = Camera::new((VIEW_WIDTH, VIEW_HEIGHT), size.width, size.height);
 
+        // Create the uniform buffer
         let uniforms = Uniforms {
-            aspect_ratio: size.width as f32 / size.height as f32, /* Your initial aspect ratio */
+            view_proj: camera.view_proj(1.0),
         };
         let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Uniform Buffer"),
@@ -198,80 +248,17 @@ impl App {
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc(), Instance::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent {
-                            src_factor: wgpu::BlendFactor::SrcAlpha,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                        alpha: wgpu::BlendComponent {
-                            src_factor: wgpu::BlendFactor::One,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                // Setting this to anything other than Fill requires Features::POLYGON_MODE_LINE
-                // or Features::POLYGON_MODE_POINT
-                polygon_mode: wgpu::PolygonMode::Fill,
-                // Requires Features::DEPTH_CLIP_CONTROL
-                unclipped_depth: false,
-                // Requires Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            // If the pipeline will be used with a multiview render pass, this
-            // indicates how many array layers the attachments will have.
-            multiview: None,
-        });
-
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            // contents: bytemuck::cast_slice(&get_vertices(aspect_ratio)),
-            contents: bytemuck::cast_slice(&vec![0u8; 1024]),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            // contents: bytemuck::cast_slice(&INDICES),
-            contents: bytemuck::cast_slice(&vec![0u8; 1024]),
-            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let max_instances = 1024;
-        let instance_size = std::mem::size_of::<Instance>();
-        let total_buffer_size = instance_size * max_instances;
+        let render_pipeline =
+            create_render_pipeline(&device, &render_pipeline_layout, &shader, config.format);
 
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Instance Buffer"),
-            contents: bytemuck::cast_slice(&vec![0u8; total_buffer_size]),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
+        let meshes = MeshRegistry::new(&device, game.config());
+        // a starting size; it grows as the game needs
+        let instance_buffer = GrowableBuffer::new(
+            &device,
+            "Instance Buffer",
+            wgpu::BufferUsages::VERTEX,
+            std::mem::size_of::<Instance>() as u64 * 1024,
+        );
 
         Self {
             game,
@@ -281,15 +268,27 @@ impl App {
             queue,
             config,
             size,
+            camera,
+            render_pipeline_layout,
             render_pipeline,
-            vertex_buffer,
-            index_buffer,
+            meshes,
+            batcher: SpriteBatcher::new(),
             instance_buffer,
             uniform_buffer,
 
             bind_group,
             uniform_bind_group,
 
+            record_dir: options.record.clone(),
+            recorder: None,
+
+            #[cfg(not(target_arch = "wasm32"))]
+            hot_reload: options
+                .watch
+                .then(|| HotReload::new(options.config.as_deref())),
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_config: None,
+
             window,
         }
     }
@@ -298,12 +297,73 @@ impl App {
         &self.window
     }
 
+    /// Applies any edits to the config file or shader picked up since the
+    /// last call.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn hot_reload(&mut self) {
+        let Some(hot_reload) = &mut self.hot_reload else {
+            return;
+        };
+
+        for reload in hot_reload.poll() {
+            match reload {
+                // the replay header pins the config the run started with
+                Reload::Config(config) if self.recorder.is_some() => {
+                    log::info!("config changed; applying it once this recording ends");
+                    self.pending_config = Some(config);
+                }
+                Reload::Config(config) => self.apply_config(config),
+                Reload::Shader(source) => self.reload_shader(&source),
+            }
+        }
+    }
+
+    /// Swaps in new tuning values, rebuilding what's sized from them.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn apply_config(&mut self, config: Config) {
+        let old = self.game.config();
+        if (old.window_width, old.window_height) != (config.window_width, config.window_height) {
+            self.window
+                .set_inner_size(LogicalSize::new(config.window_width, config.window_height));
+        }
+        self.meshes = MeshRegistry::new(&self.device, &config);
+        self.game.set_config(config);
+        log::info!("reloaded config");
+    }
+
+    /// Swaps in a new shader, keeping the old pipeline if it doesn't compile.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_shader(&mut self, source: &str) {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Shader"),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+        let render_pipeline = create_render_pipeline(
+            &self.device,
+            &self.render_pipeline_layout,
+            &shader,
+            self.config.format,
+        );
+
+        match pollster::block_on(self.device.pop_error_scope()) {
+            Some(e) => log::error!("shader reload failed, keeping the old one: {}", e),
+            None => {
+                self.render_pipeline = render_pipeline;
+                log::info!("reloaded shader");
+            }
+        }
+    }
+
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            self.camera.resize(new_size.width, new_size.height);
         }
     }
 
@@ -320,10 +380,18 @@ impl App {
             } => {
                 match state {
                     ElementState::Pressed => {
-                        self.game.keys.insert(*key);
-
-                        if key == &VirtualKeyCode::Space {
-                            self.game.toggle_pause();
+                        if self.game.keys.insert(*key) {
+                            self.game.key_pressed(*key);
+
+                            match key {
+                                VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd => {
+                                    self.camera.zoom_by(ZOOM_STEP)
+                                }
+                                VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => {
+                                    self.camera.zoom_by(1.0 / ZOOM_STEP)
+                                }
+                                _ => {}
+                            }
                         }
                     }
                     ElementState::Released => {
@@ -337,10 +405,93 @@ impl App {
     }
 
     pub fn update(&mut self, dt: f32) {
+        let simulating = self.game.state().simulates();
+        if simulating && self.recorder.is_none() {
+            self.start_recording(dt);
+        }
+
         self.game.update(dt);
+
+        for event in self.game.events() {
+            self.camera.react(event);
+        }
+        self.camera.update(
+            dt,
+            self.game.player.game_object.coords,
+            &self.game.mode().arena,
+        );
+
+        if let Some(recorder) = self.recorder.as_mut().filter(|_| simulating) {
+            if let Err(e) = recorder.record(&self.game) {
+                log::error!("stopped recording: {}", e);
+                self.recorder = None;
+            }
+        }
+
+        if matches!(self.game.state(), GameState::Title | GameState::GameOver) {
+            self.finish_recording();
+        }
+    }
+
+    fn start_recording(&mut self, dt: f32) {
+        let Some(dir) = &self.record_dir else {
+            return;
+        };
+
+        let path = dir.join(format!(
+            "{}-{}.replay.jsonl",
+            self.game.seed(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        ));
+
+        match File::create(&path)
+            .and_then(|file| Recorder::new(BufWriter::new(file), &self.game, dt))
+        {
+            Ok(recorder) => {
+                log::info!("recording to {}", path.display());
+                self.recorder = Some(recorder);
+            }
+            Err(e) => log::error!("couldn't record to {}: {}", path.display(), e),
+        }
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    fn finish_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            let ticks = recorder.ticks();
+            if let Err(e) = recorder.finish() {
+                log::error!("couldn't finish recording: {}", e);
+            } else {
+                log::info!("recorded {} ticks", ticks);
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(config) = self.pending_config.take() {
+            self.apply_config(config);
+        }
+    }
+
+    pub fn render(&mut self, alpha: f32) -> Result<(), wgpu::SurfaceError> {
+        self.batcher
+            .build(self.game.sprites(), self.game.config(), alpha);
+
+        // upload before the pass borrows the buffer, so it's free to grow
+        self.instance_buffer.write(
+            &self.device,
+            &self.queue,
+            bytemuck::cast_slice(self.batcher.instances()),
+        );
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[Uniforms {
+                view_proj: self.camera.view_proj(alpha),
+            }]),
+        );
+
         let output = self.surface.get_current_texture()?;
         let view = output
             .texture
@@ -373,131 +524,35 @@ impl App {
                 timestamp_writes: None,
             });
 
+            let viewport = self.camera.viewport();
+            render_pass.set_viewport(
+                viewport.x,
+                viewport.y,
+                viewport.width,
+                viewport.height,
+                0.0,
+                1.0,
+            );
+
             render_pass.set_pipeline(&self.render_pipeline);
 
             render_pass.set_bind_group(0, &self.bind_group, &[]);
             render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-
-            let mut vertex_offset = 0u64;
-            let mut index_offset = 0u64;
-            let mut instance_offset = 0u64;
-            let mut base_vertex_offset = 0i32;
-            let mut instance_count = 0u32;
-
-            let vertices = Player::get_vertices();
-            let indices = Player::get_indices();
-            let instance = self.game.player.get_instance();
-
-            self.queue.write_buffer(
-                &self.vertex_buffer,
-                vertex_offset,
-                bytemuck::cast_slice(&vertices),
-            );
-            self.queue.write_buffer(
-                &self.index_buffer,
-                index_offset,
-                bytemuck::cast_slice(&indices),
+            render_pass.set_vertex_buffer(0, self.meshes.vertex_buffer().slice(..));
+            render_pass.set_vertex_buffer(1, self.instance_buffer.buffer().slice(..));
+            render_pass.set_index_buffer(
+                self.meshes.index_buffer().slice(..),
+                wgpu::IndexFormat::Uint16,
             );
-            self.queue.write_buffer(
-                &self.instance_buffer,
-                instance_offset,
-                bytemuck::cast_slice(&vec![instance]),
-            );
-            self.queue.write_buffer(
-                &self.uniform_buffer,
-                0,
-                bytemuck::cast_slice(&[Uniforms {
-                    aspect_ratio: self.size.width as f32 / self.size.height as f32,
-                }]),
-            );
-
-            vertex_offset += std::mem::size_of::<Vertex>() as u64 * vertices.len() as u64;
-            index_offset += std::mem::size_of::<u16>() as u64 * indices.len() as u64;
-            instance_offset += std::mem::size_of::<Instance>() as u64; // 1 instance
 
-            render_pass.draw_indexed(0..indices.len() as u32, base_vertex_offset, 0..1);
-            base_vertex_offset += vertices.len() as i32;
-            instance_count += 1;
-
-            let vertices = Enemy::get_vertices();
-            let indices = Enemy::get_indices();
-            let mut instances: Vec<Instance> = Vec::new();
-            for enemy in &self.game.enemies {
-                instances.push(enemy.get_instance());
+            for batch in self.batcher.batches() {
+                let mesh = self.meshes.get(batch.mesh);
+                render_pass.draw_indexed(
+                    mesh.indices.clone(),
+                    mesh.base_vertex,
+                    batch.instances.clone(),
+                );
             }
-
-            self.queue.write_buffer(
-                &self.vertex_buffer,
-                vertex_offset,
-                bytemuck::cast_slice(&vertices),
-            );
-            self.queue.write_buffer(
-                &self.index_buffer,
-                index_offset,
-                bytemuck::cast_slice(&indices),
-            );
-            self.queue.write_buffer(
-                &self.instance_buffer,
-                instance_offset,
-                bytemuck::cast_slice(&instances),
-            );
-
-            vertex_offset += std::mem::size_of::<Vertex>() as u64 * vertices.len() as u64;
-            index_offset += std::mem::size_of::<u16>() as u64 * indices.len() as u64;
-            instance_offset += std::mem::size_of::<Instance>() as u64 * instances.len() as u64;
-
-            render_pass.draw_indexed(
-                0..indices.len() as u32,
-                base_vertex_offset,
-                instance_count..(instance_count + instances.len() as u32),
-            );
-            base_vertex_offset += vertices.len() as i32;
-            instance_count += instances.len() as u32;
-
-            let vertices = self
-                .game
-                .gates
-                .iter()
-                .flat_map(|_| Gate::get_vertices())
-                .collect::<Vec<_>>();
-            let indices = Gate::get_indices();
-            let instances = self
-                .game
-                .gates
-                .iter()
-                .map(|gate| gate.get_instance())
-                .collect::<Vec<_>>();
-
-            self.queue.write_buffer(
-                &self.vertex_buffer,
-                vertex_offset,
-                bytemuck::cast_slice(&vertices),
-            );
-            self.queue.write_buffer(
-                &self.index_buffer,
-                index_offset,
-                bytemuck::cast_slice(&indices),
-            );
-            self.queue.write_buffer(
-                &self.instance_buffer,
-                instance_offset,
-                bytemuck::cast_slice(&instances),
-            );
-
-            vertex_offset += std::mem::size_of::<Vertex>() as u64 * vertices.len() as u64;
-            index_offset += std::mem::size_of::<u16>() as u64 * indices.len() as u64;
-            instance_offset += std::mem::size_of::<Instance>() as u64 * instances.len() as u64;
-
-            render_pass.draw_indexed(
-                0..indices.len() as u32,
-                base_vertex_offset,
-                instance_count..(instance_count + instances.len() as u32),
-            );
-            base_vertex_offset += vertices.len() as i32;
-            instance_count += instances.len() as u32;
         }
 
         self.queue.submit(iter::once(encoder.finish()));
@@ -506,3 +561,62 @@ impl App {
         Ok(())
     }
 }
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc(), Instance::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            // Setting this to anything other than Fill requires Features::POLYGON_MODE_LINE
+            // or Features::POLYGON_MODE_POINT
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        // If the pipeline will be used with a multiview render pass, this
+        // indicates how many array layers the attachments will have.
+        multiview: None,
+    })
+}
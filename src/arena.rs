@@ -0,0 +1,39 @@
+use rand::Rng;
+
+/// The playable rectangle in world units, centred on the origin.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Arena {
+    pub half_width: f32,
+    pub half_height: f32,
+}
+
+impl Arena {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            half_width: width / 2.0,
+            half_height: height / 2.0,
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        2.0 * self.half_width
+    }
+
+    pub fn height(&self) -> f32 {
+        2.0 * self.half_height
+    }
+
+    /// Pushes a circle of `radius` at `coords` back inside the walls.
+    pub fn clamp(&self, coords: (f32, f32), radius: f32) -> (f32, f32) {
+        let x_max = (self.half_width - radius).max(0.0);
+        let y_max = (self.half_height - radius).max(0.0);
+        (coords.0.clamp(-x_max, x_max), coords.1.clamp(-y_max, y_max))
+    }
+
+    /// A uniformly random point at least `margin` away from every wall.
+    pub fn sample(&self, rng: &mut impl Rng, margin: f32) -> (f32, f32) {
+        let x_max = (self.half_width - margin).max(f32::EPSILON);
+        let y_max = (self.half_height - margin).max(f32::EPSILON);
+        (rng.gen_range(-x_max..x_max), rng.gen_range(-y_max..y_max))
+    }
+}
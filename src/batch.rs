@@ -0,0 +1,67 @@
+use crate::config::Config;
+use crate::mesh::MeshId;
+use crate::sprite::{Instance, Sprite};
+
+use std::ops::Range;
+
+/// A run of instances that all share a mesh, drawn with one `draw_indexed`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Batch {
+    pub mesh: MeshId,
+    pub instances: Range<u32>,
+}
+
+/// Groups sprites by mesh and packs their instances so each group is one
+/// contiguous range. Batches come out in `MeshId` order, which is the order
+/// they're drawn in; sprites keep their relative order within a batch.
+#[derive(Default)]
+pub struct SpriteBatcher {
+    sorted: Vec<(MeshId, Instance)>,
+    instances: Vec<Instance>,
+    batches: Vec<Batch>,
+}
+
+impl SpriteBatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the previous frame's batches with `sprites`, as they are
+    /// `alpha` of the way between simulation ticks.
+    pub fn build<'a>(
+        &mut self,
+        sprites: impl IntoIterator<Item = &'a dyn Sprite>,
+        config: &Config,
+        alpha: f32,
+    ) {
+        self.sorted.clear();
+        self.sorted.extend(
+            sprites
+                .into_iter()
+                .map(|sprite| (sprite.mesh(), sprite.get_instance(alpha, config))),
+        );
+        self.sorted.sort_by_key(|&(mesh, _)| mesh);
+
+        self.instances.clear();
+        self.batches.clear();
+        for (i, &(mesh, instance)) in self.sorted.iter().enumerate() {
+            let i = i as u32;
+            match self.batches.last_mut() {
+                Some(batch) if batch.mesh == mesh => batch.instances.end = i + 1,
+                _ => self.batches.push(Batch {
+                    mesh,
+                    instances: i..i + 1,
+                }),
+            }
+            self.instances.push(instance);
+        }
+    }
+
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    pub fn batches(&self) -> &[Batch] {
+        &self.batches
+    }
+}
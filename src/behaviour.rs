@@ -0,0 +1,311 @@
+use crate::arena::Arena;
+use crate::config::Config;
+use crate::game_object::{Enemy, EnemyKind, GameObject, Gate};
+use crate::spatial::SpatialGrid;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::f32::consts::TAU;
+
+/// What an enemy can see of the world when deciding where to go.
+pub struct Surroundings<'a> {
+    pub player: (f32, f32),
+    pub gates: &'a [Gate],
+    /// Gate positions, for neighbour queries.
+    pub gate_grid: &'a SpatialGrid,
+    pub arena: &'a Arena,
+    pub config: &'a Config,
+    pub dt: f32,
+}
+
+/// Movement logic for one enemy. Each enemy owns its behaviour, so any state
+/// it needs (a heading, a wind-up timer) lives here rather than in `Game`.
+pub trait Behaviour: Send {
+    /// Velocity for this tick, in world units per second. Randomness must
+    /// come from `rng` to keep runs reproducible.
+    fn steer(&mut self, body: &GameObject, world: &Surroundings, rng: &mut StdRng) -> (f32, f32);
+
+    /// Sprite rotation, for behaviours that want to show what they're doing.
+    fn rotation(&self) -> f32 {
+        0.0
+    }
+
+    /// Enemies left behind when this one is killed, placed inside `arena`.
+    fn on_death(
+        &self,
+        _body: &GameObject,
+        _arena: &Arena,
+        _config: &Config,
+        _rng: &mut StdRng,
+    ) -> Vec<Enemy> {
+        Vec::new()
+    }
+}
+
+/// The behaviour a freshly spawned enemy of `kind` starts with.
+pub fn for_kind(kind: EnemyKind) -> Box<dyn Behaviour> {
+    match kind {
+        EnemyKind::Seeker => Box::new(Seeker),
+        EnemyKind::Wanderer => Box::new(Wanderer::default()),
+        EnemyKind::Charger => Box::new(Charger::default()),
+        EnemyKind::Splitter => Box::new(Splitter { generations: 1 }),
+        EnemyKind::GateAvoider => Box::new(GateAvoider),
+    }
+}
+
+pub fn rescale(v: (f32, f32), speed: f32) -> (f32, f32) {
+    let d = (v.0 * v.0 + v.1 * v.1).sqrt();
+    (
+        v.0 * speed / (d + f32::EPSILON),
+        v.1 * speed / (d + f32::EPSILON),
+    )
+}
+
+fn towards(from: (f32, f32), to: (f32, f32), speed: f32) -> (f32, f32) {
+    rescale((to.0 - from.0, to.1 - from.1), speed)
+}
+
+/// Homes straight in on the player.
+pub struct Seeker;
+
+impl Behaviour for Seeker {
+    fn steer(&mut self, body: &GameObject, world: &Surroundings, _: &mut StdRng) -> (f32, f32) {
+        towards(body.coords, world.player, world.config.enemy_speed)
+    }
+}
+
+/// Drifts about at random, ignoring the player, and turns back from walls.
+#[derive(Default)]
+pub struct Wanderer {
+    heading: Option<f32>,
+}
+
+impl Wanderer {
+    const SPEED: f32 = 0.7;
+    /// Standard deviation-ish of the heading's random walk, radians per second.
+    const TURN_RATE: f32 = 2.5;
+}
+
+impl Behaviour for Wanderer {
+    fn steer(&mut self, body: &GameObject, world: &Surroundings, rng: &mut StdRng) -> (f32, f32) {
+        let heading = self.heading.get_or_insert_with(|| rng.gen_range(0.0..TAU));
+        *heading += rng.gen_range(-1.0..1.0) * Self::TURN_RATE * world.dt.sqrt();
+
+        let (x, y) = body.coords;
+        let margin = world.config.enemy_radius;
+        let (mut dx, mut dy) = (heading.cos(), heading.sin());
+        if (x >= world.arena.half_width - margin && dx > 0.0)
+            || (x <= -world.arena.half_width + margin && dx < 0.0)
+        {
+            dx = -dx;
+        }
+        if (y >= world.arena.half_height - margin && dy > 0.0)
+            || (y <= -world.arena.half_height + margin && dy < 0.0)
+        {
+            dy = -dy;
+        }
+        *heading = dy.atan2(dx);
+
+        rescale((dx, dy), Self::SPEED * world.config.enemy_speed)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum ChargePhase {
+    Approach,
+    /// Stopped and shaking, about to dash along `direction`.
+    Telegraph {
+        direction: (f32, f32),
+    },
+    Dash {
+        direction: (f32, f32),
+    },
+    Recover,
+}
+
+/// Closes in slowly, stops to telegraph, then dashes at where the player was.
+pub struct Charger {
+    phase: ChargePhase,
+    phase_time: f32,
+}
+
+impl Default for Charger {
+    fn default() -> Self {
+        Self {
+            phase: ChargePhase::Approach,
+            phase_time: 0.0,
+        }
+    }
+}
+
+impl Charger {
+    const APPROACH_SPEED: f32 = 0.5;
+    const DASH_SPEED: f32 = 4.0;
+    /// Distance from the player at which the wind-up starts.
+    const RANGE: f32 = 0.7;
+    const TELEGRAPH_TIME: f32 = 0.6;
+    const DASH_TIME: f32 = 0.35;
+    const RECOVER_TIME: f32 = 0.8;
+
+    fn enter(&mut self, phase: ChargePhase) {
+        self.phase = phase;
+        self.phase_time = 0.0;
+    }
+}
+
+impl Behaviour for Charger {
+    fn steer(&mut self, body: &GameObject, world: &Surroundings, _: &mut StdRng) -> (f32, f32) {
+        self.phase_time += world.dt;
+        let speed = world.config.enemy_speed;
+        let (dx, dy) = (
+            world.player.0 - body.coords.0,
+            world.player.1 - body.coords.1,
+        );
+
+        match self.phase {
+            ChargePhase::Approach => {
+                if dx * dx + dy * dy < Self::RANGE * Self::RANGE {
+                    self.enter(ChargePhase::Telegraph {
+                        direction: rescale((dx, dy), 1.0),
+                    });
+                    return (0.0, 0.0);
+                }
+                rescale((dx, dy), Self::APPROACH_SPEED * speed)
+            }
+            ChargePhase::Telegraph { direction } => {
+                if self.phase_time >= Self::TELEGRAPH_TIME {
+                    self.enter(ChargePhase::Dash { direction });
+                }
+                (0.0, 0.0)
+            }
+            ChargePhase::Dash { direction } => {
+                if self.phase_time >= Self::DASH_TIME {
+                    self.enter(ChargePhase::Recover);
+                }
+                rescale(direction, Self::DASH_SPEED * speed)
+            }
+            ChargePhase::Recover => {
+                if self.phase_time >= Self::RECOVER_TIME {
+                    self.enter(ChargePhase::Approach);
+                }
+                (0.0, 0.0)
+            }
+        }
+    }
+
+    fn rotation(&self) -> f32 {
+        match self.phase {
+            // shake harder as the dash gets closer
+            ChargePhase::Telegraph { .. } => {
+                let t = self.phase_time / Self::TELEGRAPH_TIME;
+                0.4 * t * (self.phase_time * 60.0).sin()
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Seeks like a `Seeker`, but splits in two when killed until it runs out
+/// of generations.
+pub struct Splitter {
+    pub generations: u32,
+}
+
+impl Splitter {
+    /// How far apart the two halves land.
+    const SPREAD: f32 = 0.1;
+}
+
+impl Behaviour for Splitter {
+    fn steer(&mut self, body: &GameObject, world: &Surroundings, _: &mut StdRng) -> (f32, f32) {
+        towards(body.coords, world.player, world.config.enemy_speed)
+    }
+
+    fn on_death(
+        &self,
+        body: &GameObject,
+        arena: &Arena,
+        config: &Config,
+        rng: &mut StdRng,
+    ) -> Vec<Enemy> {
+        if self.generations == 0 {
+            return Vec::new();
+        }
+
+        let angle = rng.gen_range(0.0..TAU);
+        let offset = (Self::SPREAD * angle.cos(), Self::SPREAD * angle.sin());
+        let (x, y) = body.coords;
+
+        [(x + offset.0, y + offset.1), (x - offset.0, y - offset.1)]
+            .into_iter()
+            .map(|coords| {
+                Enemy::with_behaviour(
+                    EnemyKind::Splitter,
+                    arena.clamp(coords, config.enemy_radius),
+                    Box::new(Splitter {
+                        generations: self.generations - 1,
+                    }),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Heads for the player while keeping its distance from gates, so it has to
+/// be lured into a blast rather than wandering into one.
+pub struct GateAvoider;
+
+impl GateAvoider {
+    /// Gates closer than this many gate radii push the enemy away.
+    const AVOID_RADII: f32 = 2.5;
+    const AVOID_WEIGHT: f32 = 2.0;
+}
+
+impl Behaviour for GateAvoider {
+    fn steer(&mut self, body: &GameObject, world: &Surroundings, _: &mut StdRng) -> (f32, f32) {
+        let (x, y) = body.coords;
+        let mut direction = towards(body.coords, world.player, 1.0);
+
+        let avoid = Self::AVOID_RADII * world.config.gate_radius;
+        world.gate_grid.for_each_within(body.coords, avoid, |i| {
+            let (gx, gy) = world.gate_grid.point(i);
+            let (dx, dy) = (x - gx, y - gy);
+            let distance = (dx * dx + dy * dy).sqrt();
+            if distance > f32::EPSILON {
+                let push = Self::AVOID_WEIGHT * (1.0 - distance / avoid);
+                direction.0 += dx / distance * push;
+                direction.1 += dy / distance * push;
+            }
+        });
+
+        rescale(direction, world.config.enemy_speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn splitter_children_stay_in_the_arena() {
+        let arena = Arena::new(2.0, 2.0);
+        let config = Config::default();
+        let corner = GameObject::new((1.0 - config.enemy_radius, 1.0 - config.enemy_radius));
+
+        for seed in 0..20 {
+            let children = Splitter { generations: 1 }.on_death(
+                &corner,
+                &arena,
+                &config,
+                &mut StdRng::seed_from_u64(seed),
+            );
+
+            assert_eq!(children.len(), 2);
+            for child in children {
+                let coords = child.game_object.coords;
+                assert_eq!(arena.clamp(coords, config.enemy_radius), coords);
+            }
+        }
+    }
+}
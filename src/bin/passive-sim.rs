@@ -0,0 +1,198 @@
+//! Runs games headlessly and prints their final stats as JSON lines.
+//!
+//!     passive-sim [--mode NAME] [--waves FILE] [--config FILE] [--seed N] [--runs N] [--ticks N] [--script FILE]
+//!     passive-sim --replay FILE
+//!
+//! Scripts are lines of `<ticks> <keys>`, e.g. `120 WD` holds W and D for
+//! 120 ticks; `-` holds nothing. Once the script runs out the player idles.
+
+use anyhow::{anyhow, bail, Context, Result};
+use passive::config::Config;
+use passive::constants::TICK_RATE;
+use passive::game::Game;
+use passive::mode::GameMode;
+use passive::replay::Replay;
+use passive::state::GameState;
+use passive::waves::WaveSchedule;
+use serde_json::json;
+use std::collections::HashSet;
+use winit::event::VirtualKeyCode;
+
+struct Args {
+    config: Config,
+    mode: GameMode,
+    seed: u64,
+    runs: u64,
+    ticks: u64,
+    script: Option<String>,
+    replay: Option<String>,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut parsed = Args {
+        config: Config::default(),
+        mode: GameMode::default(),
+        seed: 0,
+        runs: 1,
+        ticks: 60 * TICK_RATE as u64,
+        script: None,
+        replay: None,
+    };
+
+    // combined after the loop so --waves applies whichever order it came in
+    let mut mode_name = None;
+    let mut waves = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow!("{} expects a value", arg))
+        };
+        match arg.as_str() {
+            "--mode" => mode_name = Some(value()?),
+            "--waves" => waves = Some(value()?),
+            "--config" => {
+                let path = value()?;
+                let source =
+                    std::fs::read_to_string(&path).with_context(|| format!("reading {}", path))?;
+                parsed.config = Config::parse(&source).with_context(|| format!("in {}", path))?;
+            }
+            "--seed" => parsed.seed = value()?.parse()?,
+            "--runs" => parsed.runs = value()?.parse()?,
+            "--ticks" => parsed.ticks = value()?.parse()?,
+            "--script" => parsed.script = Some(value()?),
+            "--replay" => parsed.replay = Some(value()?),
+            _ => bail!("unknown argument {}", arg),
+        }
+    }
+
+    if let Some(name) = mode_name {
+        parsed.mode = GameMode::by_name(&name).ok_or_else(|| anyhow!("unknown mode {}", name))?;
+    }
+    if let Some(path) = waves {
+        parsed.mode.waves =
+            WaveSchedule::load(&path).with_context(|| format!("loading {}", path))?;
+    }
+
+    Ok(parsed)
+}
+
+fn parse_script(source: &str) -> Result<Vec<(u64, HashSet<VirtualKeyCode>)>> {
+    let mut steps = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (ticks, keys) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| anyhow!("line {}: expected `<ticks> <keys>`", i + 1))?;
+        let ticks = ticks
+            .parse()
+            .with_context(|| format!("line {}: bad tick count", i + 1))?;
+
+        let mut held = HashSet::new();
+        for c in keys.trim().chars() {
+            held.insert(match c.to_ascii_uppercase() {
+                'W' => VirtualKeyCode::W,
+                'A' => VirtualKeyCode::A,
+                'S' => VirtualKeyCode::S,
+                'D' => VirtualKeyCode::D,
+                '-' => continue,
+                _ => bail!("line {}: unknown key {:?}", i + 1, c),
+            });
+        }
+
+        steps.push((ticks, held));
+    }
+
+    Ok(steps)
+}
+
+fn simulate(
+    config: &Config,
+    mode: &GameMode,
+    seed: u64,
+    ticks: u64,
+    script: &[(u64, HashSet<VirtualKeyCode>)],
+) -> serde_json::Value {
+    let dt = 1.0 / TICK_RATE;
+    let mut game = Game::with_config(config.clone(), mode.clone(), seed);
+    game.start();
+
+    let mut steps = script.iter();
+    let mut step = steps.next();
+    let mut step_left = step.map_or(0, |(n, _)| *n);
+
+    let mut played = 0;
+    while played < ticks && game.state() == GameState::Playing {
+        while step.is_some() && step_left == 0 {
+            step = steps.next();
+            step_left = step.map_or(0, |(n, _)| *n);
+        }
+        game.keys = step.map(|(_, keys)| keys.clone()).unwrap_or_default();
+        step_left = step_left.saturating_sub(1);
+
+        game.update(dt);
+        played += 1;
+    }
+
+    json!({
+        "mode": mode.name,
+        "seed": seed,
+        "ticks": played,
+        "game_over": game.state() == GameState::GameOver,
+        "enemies": game.enemies.len(),
+        "gates": game.gates.len(),
+        "stats": game.stats(),
+    })
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = parse_args()?;
+
+    if let Some(path) = args.replay {
+        let replay = Replay::load(&path).with_context(|| format!("loading {}", path))?;
+        let game = replay.play()?;
+
+        println!(
+            "{}",
+            json!({
+                "mode": replay.header.mode,
+                "seed": replay.header.seed,
+                "ticks": replay.ticks.len(),
+                "game_over": game.state() == GameState::GameOver,
+                "enemies": game.enemies.len(),
+                "gates": game.gates.len(),
+                "stats": game.stats(),
+            })
+        );
+        return Ok(());
+    }
+
+    let script = match &args.script {
+        Some(path) => parse_script(
+            &std::fs::read_to_string(path).with_context(|| format!("reading {}", path))?,
+        )?,
+        None => Vec::new(),
+    };
+
+    for run in 0..args.runs {
+        println!(
+            "{}",
+            simulate(
+                &args.config,
+                &args.mode,
+                args.seed.wrapping_add(run),
+                args.ticks,
+                &script
+            )
+        );
+    }
+
+    Ok(())
+}
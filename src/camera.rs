@@ -0,0 +1,307 @@
+use crate::arena::Arena;
+use crate::event::GameEvent;
+
+/// A pixel rectangle of the window, origin at the top left.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// How strongly the camera responds to what happens in the game. Trauma is
+/// in [0, 1]; shake grows with its square.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraEffects {
+    /// How quickly the view catches up with the player, per second.
+    pub follow_rate: f32,
+    pub zoom_rate: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    /// Largest shake offset, in world units, at full trauma.
+    pub max_shake: f32,
+    pub trauma_decay: f32,
+    pub detonation_trauma: f32,
+    pub trauma_per_kill: f32,
+    pub death_trauma: f32,
+}
+
+impl Default for CameraEffects {
+    fn default() -> Self {
+        Self {
+            follow_rate: 4.0,
+            zoom_rate: 6.0,
+            min_zoom: 0.5,
+            max_zoom: 2.0,
+            max_shake: 0.08,
+            trauma_decay: 1.5,
+            detonation_trauma: 0.25,
+            trauma_per_kill: 0.03,
+            death_trauma: 0.8,
+        }
+    }
+}
+
+/// Orthographic 2D camera. Keeps a fixed world-space area visible whatever
+/// the window shape, letterboxing or pillarboxing the rest.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    /// World point at the centre of the view.
+    pub center: (f32, f32),
+    /// `center` as of the previous update, for interpolating between ticks.
+    pub prev_center: (f32, f32),
+    /// World-space width and height that fill the viewport at zoom 1.
+    pub view_size: (f32, f32),
+    pub effects: CameraEffects,
+    zoom: f32,
+    target_zoom: f32,
+    trauma: f32,
+    time: f32,
+    screen_size: (f32, f32),
+}
+
+impl Camera {
+    pub fn new(view_size: (f32, f32), screen_width: u32, screen_height: u32) -> Self {
+        Self {
+            center: (0.0, 0.0),
+            prev_center: (0.0, 0.0),
+            view_size,
+            effects: CameraEffects::default(),
+            zoom: 1.0,
+            target_zoom: 1.0,
+            trauma: 0.0,
+            time: 0.0,
+            screen_size: (screen_width as f32, screen_height as f32),
+        }
+    }
+
+    pub fn resize(&mut self, screen_width: u32, screen_height: u32) {
+        self.screen_size = (screen_width as f32, screen_height as f32);
+    }
+
+    /// World-space width and height currently on screen.
+    pub fn visible_size(&self) -> (f32, f32) {
+        (self.view_size.0 / self.zoom, self.view_size.1 / self.zoom)
+    }
+
+    /// Multiplies the zoom the camera eases towards; > 1 zooms in.
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.target_zoom =
+            (self.target_zoom * factor).clamp(self.effects.min_zoom, self.effects.max_zoom);
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// Turns game events into camera effects.
+    pub fn react(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::GateDetonated(detonation) => self.add_trauma(
+                self.effects.detonation_trauma
+                    + self.effects.trauma_per_kill * detonation.kills as f32,
+            ),
+            GameEvent::PlayerDied { .. } => self.add_trauma(self.effects.death_trauma),
+            _ => {}
+        }
+    }
+
+    /// Eases towards `target` and the requested zoom, never showing past the
+    /// arena walls on an axis where the arena is bigger than the view.
+    pub fn update(&mut self, dt: f32, target: (f32, f32), arena: &Arena) {
+        self.prev_center = self.center;
+        self.time += dt;
+        self.trauma = (self.trauma - self.effects.trauma_decay * dt).max(0.0);
+
+        let zoom_blend = 1.0 - (-self.effects.zoom_rate * dt).exp();
+        self.zoom += (self.target_zoom - self.zoom) * zoom_blend;
+
+        let follow_blend = 1.0 - (-self.effects.follow_rate * dt).exp();
+        let (visible_width, visible_height) = self.visible_size();
+        let follow = |center: f32, target: f32, half_arena: f32, visible: f32| {
+            let slack = half_arena - visible / 2.0;
+            if slack <= 0.0 {
+                0.0
+            } else {
+                (center + (target - center) * follow_blend).clamp(-slack, slack)
+            }
+        };
+
+        self.center = (
+            follow(self.center.0, target.0, arena.half_width, visible_width),
+            follow(self.center.1, target.1, arena.half_height, visible_height),
+        );
+    }
+
+    /// The largest centred part of the window with the view's aspect ratio.
+    pub fn viewport(&self) -> Viewport {
+        let (sw, sh) = self.screen_size;
+        let aspect = self.view_size.0 / self.view_size.1;
+
+        if sw / sh > aspect {
+            let width = sh * aspect;
+            Viewport {
+                x: (sw - width) / 2.0,
+                y: 0.0,
+                width,
+                height: sh,
+            }
+        } else {
+            let height = sw / aspect;
+            Viewport {
+                x: 0.0,
+                y: (sh - height) / 2.0,
+                width: sw,
+                height,
+            }
+        }
+    }
+
+    /// Current shake displacement. Sums of incommensurate sines stand in for
+    /// noise so the shake doesn't touch the game's seeded RNG.
+    fn shake_offset(&self) -> (f32, f32) {
+        let amount = self.effects.max_shake * self.trauma * self.trauma;
+        let t = self.time;
+        (
+            amount * ((t * 47.0).sin() + 0.5 * (t * 83.0).sin()) / 1.5,
+            amount * ((t * 53.0).cos() + 0.5 * (t * 97.0).cos()) / 1.5,
+        )
+    }
+
+    /// World point drawn at the middle of the viewport: the centre
+    /// interpolated `alpha` of the way from the previous update, like the
+    /// sprites it looks at, plus shake.
+    fn eye(&self, alpha: f32) -> (f32, f32) {
+        let shake = self.shake_offset();
+        (
+            self.prev_center.0 + (self.center.0 - self.prev_center.0) * alpha + shake.0,
+            self.prev_center.1 + (self.center.1 - self.prev_center.1) * alpha + shake.1,
+        )
+    }
+
+    /// Column-major matrix taking world coordinates to clip space within the
+    /// viewport, as seen from `eye(alpha)`.
+    pub fn view_proj(&self, alpha: f32) -> [[f32; 4]; 4] {
+        let (visible_width, visible_height) = self.visible_size();
+        let (sx, sy) = (2.0 / visible_width, 2.0 / visible_height);
+        let (cx, cy) = self.eye(alpha);
+
+        [
+            [sx, 0.0, 0.0, 0.0],
+            [0.0, sy, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [-cx * sx, -cy * sy, 0.0, 1.0],
+        ]
+    }
+
+    /// Window pixel position of a world point, as drawn by
+    /// `view_proj(alpha)`.
+    pub fn world_to_screen(&self, world: (f32, f32), alpha: f32) -> (f32, f32) {
+        let viewport = self.viewport();
+        let (visible_width, visible_height) = self.visible_size();
+        let (cx, cy) = self.eye(alpha);
+        let u = (world.0 - cx) / visible_width + 0.5;
+        let v = 0.5 - (world.1 - cy) / visible_height;
+        (
+            viewport.x + u * viewport.width,
+            viewport.y + v * viewport.height,
+        )
+    }
+
+    /// World point under a window pixel position, as drawn by
+    /// `view_proj(alpha)`.
+    pub fn screen_to_world(&self, screen: (f32, f32), alpha: f32) -> (f32, f32) {
+        let viewport = self.viewport();
+        let (visible_width, visible_height) = self.visible_size();
+        let (cx, cy) = self.eye(alpha);
+        let u = (screen.0 - viewport.x) / viewport.width;
+        let v = (screen.1 - viewport.y) / viewport.height;
+        (
+            cx + (u - 0.5) * visible_width,
+            cy + (0.5 - v) * visible_height,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A camera over a 4:3 view, off centre, zoomed in and mid-shake.
+    fn camera(screen_width: u32, screen_height: u32) -> Camera {
+        let mut camera = Camera::new((4.0, 3.0), screen_width, screen_height);
+        camera.prev_center = (0.3, -0.2);
+        camera.center = (0.5, 0.1);
+        camera.zoom = 1.6;
+        camera.trauma = 0.7;
+        camera.time = 1.3;
+        camera
+    }
+
+    fn assert_close(a: (f32, f32), b: (f32, f32)) {
+        assert!(
+            (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    /// Where `view_proj` puts `world` in the window, in pixels.
+    fn projected(camera: &Camera, world: (f32, f32), alpha: f32) -> (f32, f32) {
+        let m = camera.view_proj(alpha);
+        let clip = (
+            m[0][0] * world.0 + m[1][0] * world.1 + m[3][0],
+            m[0][1] * world.0 + m[1][1] * world.1 + m[3][1],
+        );
+        let viewport = camera.viewport();
+        (
+            viewport.x + (clip.0 + 1.0) / 2.0 * viewport.width,
+            viewport.y + (1.0 - clip.1) / 2.0 * viewport.height,
+        )
+    }
+
+    fn check_round_trip(camera: &Camera) {
+        for alpha in [0.0, 0.4, 1.0] {
+            for world in [(0.0, 0.0), (1.2, -0.7), (-2.0, 1.5)] {
+                let screen = camera.world_to_screen(world, alpha);
+                assert_close(screen, projected(camera, world, alpha));
+                assert_close(camera.screen_to_world(screen, alpha), world);
+            }
+        }
+    }
+
+    #[test]
+    fn letterboxed_round_trip() {
+        let camera = camera(800, 1200);
+        let viewport = camera.viewport();
+        assert_eq!((viewport.x, viewport.width), (0.0, 800.0));
+        assert_eq!(viewport.height, 600.0);
+        assert_eq!(viewport.y, 300.0);
+
+        check_round_trip(&camera);
+    }
+
+    #[test]
+    fn pillarboxed_round_trip() {
+        let camera = camera(1600, 600);
+        let viewport = camera.viewport();
+        assert_eq!((viewport.y, viewport.height), (0.0, 600.0));
+        assert_eq!(viewport.width, 800.0);
+        assert_eq!(viewport.x, 400.0);
+
+        check_round_trip(&camera);
+    }
+
+    #[test]
+    fn centre_maps_to_viewport_middle() {
+        let mut camera = camera(1200, 900);
+        camera.trauma = 0.0;
+        assert_close(camera.world_to_screen(camera.center, 1.0), (600.0, 450.0));
+        assert_close(
+            camera.screen_to_world((600.0, 450.0), 0.0),
+            camera.prev_center,
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use crate::config::Config;
+use crate::event::GameEvent;
+use crate::game_object::{Gate, Player};
+use crate::spatial::SpatialGrid;
+
+fn distance_sq(a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (a.0 - b.0, a.1 - b.1);
+    dx * dx + dy * dy
+}
+
+/// Twice the signed area of the triangle `abc`; positive when `c` lies to
+/// the left of the line through `a` and `b`.
+fn orientation(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> f32 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+/// Whether segments `p0p1` and `q0q1` properly intersect.
+fn segments_intersect(p0: (f32, f32), p1: (f32, f32), q0: (f32, f32), q1: (f32, f32)) -> bool {
+    let d1 = orientation(q0, q1, p0);
+    let d2 = orientation(q0, q1, p1);
+    let d3 = orientation(p0, p1, q0);
+    let d4 = orientation(p0, p1, q1);
+
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// Tests the player against nearby enemies and gates and appends what it
+/// finds to `events`. `enemy_grid` and `gate_grid` must hold the enemies' and
+/// gates' current positions. Gate crossings are swept along the player's
+/// movement relative to each gate since the previous update, so neither fast
+/// movement nor a drifting or spinning gate can tunnel through a bar.
+pub fn detect(
+    config: &Config,
+    player: &Player,
+    enemy_grid: &SpatialGrid,
+    gates: &[Gate],
+    gate_grid: &SpatialGrid,
+    events: &mut Vec<GameEvent>,
+) {
+    let p = player.game_object.coords;
+    let p_prev = player.game_object.prev_coords;
+
+    let enemy_reach = config.player_radius + config.enemy_radius;
+    enemy_grid.for_each_within(p, enemy_reach, |i| {
+        if distance_sq(p, enemy_grid.point(i)) < enemy_reach * enemy_reach {
+            events.push(GameEvent::PlayerHitEnemy { enemy: i });
+        }
+    });
+
+    // any gate the player touched or crossed has its centre within a bar's
+    // length of the player's path, give or take how far the gate moved
+    let gate_step = gates
+        .iter()
+        .map(|gate| distance_sq(gate.game_object.coords, gate.game_object.prev_coords))
+        .fold(0f32, f32::max)
+        .sqrt();
+    let endpoint_reach = config.player_radius + config.gate_endpoint_radius;
+    let gate_reach = config.gate_radius + endpoint_reach + gate_step;
+    let (e0, e1) = Gate::local_endpoints(config.gate_radius);
+    gate_grid.for_each_near_segment(p_prev, p, gate_reach, |i| {
+        // sweep the player's path in the gate's own frame, so a gate that
+        // drifted or spun over the player counts as a crossing too
+        let from = gates[i].to_prev_local(p_prev);
+        let to = gates[i].to_local(p);
+
+        if distance_sq(to, e0) < endpoint_reach * endpoint_reach
+            || distance_sq(to, e1) < endpoint_reach * endpoint_reach
+        {
+            events.push(GameEvent::PlayerTouchedGateEndpoint { gate: i });
+        }
+
+        if segments_intersect(from, to, e0, e1) {
+            events.push(GameEvent::PlayerCrossedGate { gate: i });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::Arena;
+    use crate::game_object::GateMotion;
+    use crate::sprite::Sprite;
+
+    /// Events from the player moving `from` → `to` past `gate`.
+    fn detect_gate_with(gate: &Gate, from: (f32, f32), to: (f32, f32)) -> Vec<GameEvent> {
+        let config = Config::default();
+        let arena = Arena::new(4.0, 4.0);
+
+        let mut player = Player::new();
+        player.game_object.prev_coords = from;
+        player.game_object.coords = to;
+
+        let gates = std::slice::from_ref(gate);
+        let mut gate_grid = SpatialGrid::new();
+        gate_grid.rebuild(&arena, config.gate_radius, [(0.0, 0.0)]);
+        let mut enemy_grid = SpatialGrid::new();
+        enemy_grid.rebuild(&arena, config.enemy_radius, []);
+
+        let mut events = Vec::new();
+        detect(
+            &config,
+            &player,
+            &enemy_grid,
+            gates,
+            &gate_grid,
+            &mut events,
+        );
+        events
+    }
+
+    /// Events from the player moving `from` → `to` past a static, unrotated
+    /// gate at the origin, whose bar runs from about `(-0.1, -0.17)` to
+    /// `(-0.1, 0.17)`.
+    fn detect_gate(from: (f32, f32), to: (f32, f32)) -> Vec<GameEvent> {
+        detect_gate_with(&Gate::new((0.0, 0.0), GateMotion::Static, 0.0), from, to)
+    }
+
+    #[test]
+    fn segments_crossing() {
+        assert!(segments_intersect(
+            (0.0, -1.0),
+            (0.0, 1.0),
+            (-1.0, 0.0),
+            (1.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn segments_missing() {
+        assert!(!segments_intersect(
+            (2.0, -1.0),
+            (2.0, 1.0),
+            (-1.0, 0.0),
+            (1.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn segments_touching_at_an_end_do_not_cross() {
+        assert!(!segments_intersect(
+            (1.0, 0.0),
+            (1.0, 1.0),
+            (-1.0, 0.0),
+            (1.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn parallel_segments_do_not_cross() {
+        assert!(!segments_intersect(
+            (-1.0, 0.5),
+            (1.0, 0.5),
+            (-1.0, 0.0),
+            (1.0, 0.0)
+        ));
+        assert!(!segments_intersect(
+            (-2.0, 0.0),
+            (2.0, 0.0),
+            (-1.0, 0.0),
+            (1.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn crossing_the_bar() {
+        assert_eq!(
+            detect_gate((-0.2, 0.05), (0.0, 0.05)),
+            [GameEvent::PlayerCrossedGate { gate: 0 }]
+        );
+    }
+
+    #[test]
+    fn passing_beyond_an_end_is_a_miss() {
+        assert_eq!(detect_gate((-0.2, 0.3), (0.0, 0.3)), []);
+    }
+
+    #[test]
+    fn touching_an_endpoint() {
+        assert_eq!(
+            detect_gate((-0.1, 0.25), (-0.1, 0.22)),
+            [GameEvent::PlayerTouchedGateEndpoint { gate: 0 }]
+        );
+    }
+
+    #[test]
+    fn moving_parallel_to_the_bar_is_not_a_crossing() {
+        assert_eq!(detect_gate((-0.05, -0.1), (-0.05, 0.1)), []);
+    }
+
+    #[test]
+    fn bar_matches_the_drawn_triangle() {
+        let config = Config::default();
+        let (_, mesh) = &Gate::meshes(&config)[0];
+        let corner = |k: usize| {
+            let [x, y, _] = mesh.vertices[k].position;
+            (x, y)
+        };
+        assert_eq!(
+            Gate::local_endpoints(config.gate_radius),
+            (corner(1), corner(2))
+        );
+    }
+
+    #[test]
+    fn spinning_bar_sweeps_over_still_player() {
+        // a point the back edge passes over, clear of its ends
+        let player = (0.12, 0.0);
+        let mut gate = Gate::new((0.0, 0.0), GateMotion::Static, 0.0);
+
+        let mut crossings = 0;
+        for _ in 0..400 {
+            gate.prev_rotation = gate.rotation;
+            gate.rotation += 0.05;
+
+            for event in detect_gate_with(&gate, player, player) {
+                assert_eq!(event, GameEvent::PlayerCrossedGate { gate: 0 });
+                crossings += 1;
+            }
+        }
+
+        // 20 radians is a little over three turns, and each turn the edge
+        // passes over the player going out and coming back
+        assert!((6..=7).contains(&crossings), "{} crossings", crossings);
+    }
+
+    #[test]
+    fn enemy_contact() {
+        let config = Config::default();
+        let arena = Arena::new(4.0, 4.0);
+        let mut enemy_grid = SpatialGrid::new();
+        enemy_grid.rebuild(&arena, config.enemy_radius, [(0.5, 0.0), (0.09, 0.0)]);
+
+        let mut events = Vec::new();
+        detect(
+            &config,
+            &Player::new(),
+            &enemy_grid,
+            &[],
+            &SpatialGrid::new(),
+            &mut events,
+        );
+        assert_eq!(events, [GameEvent::PlayerHitEnemy { enemy: 1 }]);
+    }
+}
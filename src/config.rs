@@ -0,0 +1,245 @@
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// Tuning values, read from a TOML file at startup. Anything the file leaves
+/// out keeps its default; see `assets/config.toml` for all of them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window_width: u32,
+    pub window_height: u32,
+
+    /// Seconds between gate spawns.
+    pub gate_spawn_freq: f32,
+    /// Seconds a gate lasts before it expires, the last `gate_warning_time`
+    /// of them fading out.
+    pub gate_lifetime: f32,
+    pub gate_warning_time: f32,
+    /// No new gates spawn while this many are on the field.
+    pub max_gates: u32,
+    /// Closest a gate may spawn to the player, and to another gate.
+    pub gate_spawn_clearance: f32,
+    pub gate_spacing: f32,
+    /// Chances a new gate drifts or orbits rather than staying put.
+    pub gate_drift_chance: f32,
+    pub gate_orbit_chance: f32,
+    /// World units per second.
+    pub gate_drift_speed: f32,
+    pub gate_orbit_radius: f32,
+    /// Radians per second.
+    pub gate_orbit_speed: f32,
+    /// Each gate spins at a speed from this range, in radians per second,
+    /// either way round.
+    pub gate_min_spin: f32,
+    pub gate_max_spin: f32,
+
+    /// World units per second.
+    pub player_speed: f32,
+    pub enemy_speed: f32,
+
+    pub player_radius: f32,
+    pub enemy_radius: f32,
+    pub gate_radius: f32,
+    pub gate_endpoint_radius: f32,
+    pub gate_blast_radius: f32,
+
+    /// Boids-style flocking. Each weight scales a steering force measured in
+    /// multiples of `enemy_speed`; 0 turns that rule off.
+    pub separation_weight: f32,
+    pub alignment_weight: f32,
+    pub cohesion_weight: f32,
+    /// Enemies closer than this push each other apart.
+    pub separation_radius: f32,
+    /// How far an enemy looks for flockmates to align and cohere with.
+    pub flock_radius: f32,
+
+    pub enemy_points: u64,
+    pub starting_lives: u32,
+    /// Seconds of protection after respawning.
+    pub respawn_invulnerability: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            window_width: 1200,
+            window_height: 900,
+            gate_spawn_freq: 4.5,
+            gate_lifetime: 20.0,
+            gate_warning_time: 3.0,
+            max_gates: 8,
+            gate_spawn_clearance: 0.6,
+            gate_spacing: 0.5,
+            gate_drift_chance: 0.3,
+            gate_orbit_chance: 0.2,
+            gate_drift_speed: 0.15,
+            gate_orbit_radius: 0.3,
+            gate_orbit_speed: 0.6,
+            gate_min_spin: 0.5,
+            gate_max_spin: 1.5,
+            player_speed: 0.54,
+            enemy_speed: 0.351,
+            player_radius: 0.05,
+            enemy_radius: 0.05,
+            gate_radius: 0.2,
+            gate_endpoint_radius: 0.02,
+            gate_blast_radius: 0.5,
+            separation_weight: 1.0,
+            alignment_weight: 0.0,
+            cohesion_weight: 0.0,
+            separation_radius: 0.12,
+            flock_radius: 0.3,
+            enemy_points: 25,
+            starting_lives: 3,
+            respawn_invulnerability: 2.0,
+        }
+    }
+}
+
+fn check<T: PartialOrd + std::fmt::Display>(
+    name: &str,
+    value: T,
+    range: RangeInclusive<T>,
+) -> anyhow::Result<()> {
+    if !range.contains(&value) {
+        bail!(
+            "{} must be between {} and {}, got {}",
+            name,
+            range.start(),
+            range.end(),
+            value
+        );
+    }
+    Ok(())
+}
+
+impl Config {
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let config: Self = toml::from_str(source)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        check("window_width", self.window_width, 160..=7680)?;
+        check("window_height", self.window_height, 120..=4320)?;
+        check("gate_spawn_freq", self.gate_spawn_freq, 0.1..=600.0)?;
+        check("gate_lifetime", self.gate_lifetime, 1.0..=600.0)?;
+        check(
+            "gate_warning_time",
+            self.gate_warning_time,
+            0.0..=self.gate_lifetime,
+        )?;
+        check("max_gates", self.max_gates, 1..=256)?;
+        check("gate_spawn_clearance", self.gate_spawn_clearance, 0.0..=5.0)?;
+        check("gate_spacing", self.gate_spacing, 0.0..=5.0)?;
+        check("gate_drift_chance", self.gate_drift_chance, 0.0..=1.0)?;
+        check(
+            "gate_orbit_chance",
+            self.gate_orbit_chance,
+            0.0..=1.0 - self.gate_drift_chance,
+        )?;
+        check("gate_drift_speed", self.gate_drift_speed, 0.0..=10.0)?;
+        check("gate_orbit_radius", self.gate_orbit_radius, 0.0..=2.0)?;
+        check("gate_orbit_speed", self.gate_orbit_speed, -20.0..=20.0)?;
+        check("gate_min_spin", self.gate_min_spin, 0.0..=20.0)?;
+        check(
+            "gate_max_spin",
+            self.gate_max_spin,
+            self.gate_min_spin..=20.0,
+        )?;
+        check("player_speed", self.player_speed, 0.0..=10.0)?;
+        check("enemy_speed", self.enemy_speed, 0.0..=10.0)?;
+        check("player_radius", self.player_radius, 0.001..=1.0)?;
+        check("enemy_radius", self.enemy_radius, 0.001..=1.0)?;
+        check("gate_radius", self.gate_radius, 0.01..=2.0)?;
+        check(
+            "gate_endpoint_radius",
+            self.gate_endpoint_radius,
+            0.0..=self.gate_radius,
+        )?;
+        check("gate_blast_radius", self.gate_blast_radius, 0.0..=10.0)?;
+        check("separation_weight", self.separation_weight, 0.0..=10.0)?;
+        check("alignment_weight", self.alignment_weight, 0.0..=10.0)?;
+        check("cohesion_weight", self.cohesion_weight, 0.0..=10.0)?;
+        check("separation_radius", self.separation_radius, 0.0..=2.0)?;
+        check("flock_radius", self.flock_radius, 0.0..=2.0)?;
+        check("enemy_points", self.enemy_points, 0..=1_000_000)?;
+        check("starting_lives", self.starting_lives, 1..=99)?;
+        check(
+            "respawn_invulnerability",
+            self.respawn_invulnerability,
+            0.0..=60.0,
+        )?;
+        Ok(())
+    }
+
+    /// Reads a config file from disk.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn load(path: &str) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path).with_context(|| format!("reading {}", path))?;
+        Self::parse(&source).with_context(|| format!("in {}", path))
+    }
+
+    /// Fetches a config file relative to the page.
+    #[cfg(target_arch = "wasm32")]
+    pub async fn load(url: &str) -> anyhow::Result<Self> {
+        use wasm_bindgen::JsCast;
+        use wasm_bindgen_futures::JsFuture;
+
+        let window = web_sys::window().context("no window")?;
+        let response: web_sys::Response = JsFuture::from(window.fetch_with_str(url))
+            .await
+            .map_err(|e| anyhow::anyhow!("fetching {}: {:?}", url, e))?
+            .dyn_into()
+            .map_err(|e| anyhow::anyhow!("fetching {}: {:?}", url, e))?;
+        if !response.ok() {
+            bail!("fetching {}: HTTP {}", url, response.status());
+        }
+
+        let text = response
+            .text()
+            .map_err(|e| anyhow::anyhow!("reading {}: {:?}", url, e))?;
+        let source = JsFuture::from(text)
+            .await
+            .map_err(|e| anyhow::anyhow!("reading {}: {:?}", url, e))?
+            .as_string()
+            .unwrap_or_default();
+
+        Self::parse(&source).with_context(|| format!("in {}", url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_config_is_the_defaults() {
+        let config = Config::parse(include_str!("../assets/config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let error = Config::parse("player_sped = 1.0").unwrap_err();
+        assert!(error.to_string().contains("player_sped"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        // ranges that hang off another key use that key's value
+        let error = Config::parse("gate_warning_time = 30.0").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "gate_warning_time must be between 0 and 20, got 30"
+        );
+
+        let error = Config::parse("starting_lives = 0").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "starting_lives must be between 1 and 99, got 0"
+        );
+    }
+}
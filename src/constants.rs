@@ -1,15 +1,10 @@
-pub const WINDOW_WIDTH: u32 = 1200;
-pub const WINDOW_HEIGHT: u32 = 900;
+// World units visible at zoom 1
+pub const VIEW_WIDTH: f32 = 8.0 / 3.0;
+pub const VIEW_HEIGHT: f32 = 2.0;
 
-pub const ENEMY_SPAWN_FREQ: f32 = 3.0;
-pub const GATE_SPAWN_FREQ: f32 = 4.5;
-pub const ENEMY_BUFFER: f32 = 0.25;
-
-pub const PLAYER_SPEED: f32 = 0.009;
-pub const ENEMY_SPEED: f32 = 0.65 * PLAYER_SPEED;
+pub const TICK_RATE: f32 = 120.0;
+pub const MAX_TICKS_PER_FRAME: u32 = 8;
 
-pub const PLAYER_RADIUS: f32 = 0.05;
-pub const ENEMY_RADIUS: f32 = 0.05;
-pub const GATE_RADIUS: f32 = 0.2;
+pub const ENEMY_BUFFER: f32 = 0.25;
 
-pub const NUM_TEXTURES: usize = 3;
+pub const NUM_TEXTURES: usize = 7;
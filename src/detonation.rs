@@ -0,0 +1,197 @@
+use crate::game_object::{Enemy, Gate};
+use crate::spatial::SpatialGrid;
+
+use std::collections::VecDeque;
+
+/// The outcome of a single gate going off.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Detonation {
+    pub coords: (f32, f32),
+    pub blast_radius: f32,
+    pub kills: u32,
+    /// 0 for a gate the player flew through, n for a gate set off by the
+    /// n-th link of a chain reaction.
+    pub chain_depth: u32,
+}
+
+/// Sets off the gates at `triggered` and any gates caught in their blasts,
+/// removing every consumed gate from `gates` and moving every enemy within
+/// `blast_radius` of a detonation from `enemies` to `dead`. Detonations are
+/// returned in the order they happened. The grids must hold the current
+/// positions of `gates` and `enemies`.
+pub fn detonate(
+    triggered: &[usize],
+    gates: &mut Vec<Gate>,
+    gate_grid: &SpatialGrid,
+    enemies: &mut Vec<Enemy>,
+    enemy_grid: &SpatialGrid,
+    dead: &mut Vec<Enemy>,
+    blast_radius: f32,
+) -> Vec<Detonation> {
+    let mut consumed = vec![false; gates.len()];
+    let mut killed = vec![false; enemies.len()];
+    let mut queue = VecDeque::new();
+    let mut detonations = Vec::new();
+
+    for &i in triggered {
+        if !consumed[i] {
+            consumed[i] = true;
+            queue.push_back((i, 0));
+        }
+    }
+
+    while let Some((i, chain_depth)) = queue.pop_front() {
+        let coords = gates[i].game_object.coords;
+
+        let mut kills = 0;
+        enemy_grid.for_each_within(coords, blast_radius, |j| {
+            if !killed[j] {
+                killed[j] = true;
+                kills += 1;
+            }
+        });
+
+        // chained gates go off in index order, whatever cells they're in
+        let mut chained = Vec::new();
+        gate_grid.for_each_within(coords, blast_radius, |j| {
+            if !consumed[j] {
+                consumed[j] = true;
+                chained.push(j);
+            }
+        });
+        chained.sort_unstable();
+        queue.extend(chained.into_iter().map(|j| (j, chain_depth + 1)));
+
+        detonations.push(Detonation {
+            coords,
+            blast_radius,
+            kills,
+            chain_depth,
+        });
+    }
+
+    let mut consumed = consumed.into_iter();
+    gates.retain(|_| !consumed.next().unwrap());
+    let (killed, alive) = enemies
+        .drain(..)
+        .zip(killed)
+        .partition::<Vec<_>, _>(|(_, dead)| *dead);
+    enemies.extend(alive.into_iter().map(|(enemy, _)| enemy));
+    dead.extend(killed.into_iter().map(|(enemy, _)| enemy));
+
+    detonations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::Arena;
+    use crate::game_object::{EnemyKind, GateMotion};
+
+    const BLAST_RADIUS: f32 = 0.5;
+
+    struct Field {
+        gates: Vec<Gate>,
+        gate_grid: SpatialGrid,
+        enemies: Vec<Enemy>,
+        enemy_grid: SpatialGrid,
+        dead: Vec<Enemy>,
+    }
+
+    impl Field {
+        fn new(gates: &[(f32, f32)], enemies: &[(f32, f32)]) -> Self {
+            let arena = Arena::new(4.0, 4.0);
+            let mut gate_grid = SpatialGrid::new();
+            gate_grid.rebuild(&arena, BLAST_RADIUS, gates.iter().copied());
+            let mut enemy_grid = SpatialGrid::new();
+            enemy_grid.rebuild(&arena, BLAST_RADIUS, enemies.iter().copied());
+
+            Self {
+                gates: gates
+                    .iter()
+                    .map(|&coords| Gate::new(coords, GateMotion::Static, 0.0))
+                    .collect(),
+                gate_grid,
+                enemies: enemies
+                    .iter()
+                    .map(|&coords| Enemy::new(EnemyKind::Seeker, coords))
+                    .collect(),
+                enemy_grid,
+                dead: Vec::new(),
+            }
+        }
+
+        fn detonate(&mut self, triggered: &[usize]) -> Vec<Detonation> {
+            detonate(
+                triggered,
+                &mut self.gates,
+                &self.gate_grid,
+                &mut self.enemies,
+                &self.enemy_grid,
+                &mut self.dead,
+                BLAST_RADIUS,
+            )
+        }
+    }
+
+    #[test]
+    fn two_gate_chain() {
+        let mut field = Field::new(
+            &[(0.0, 0.0), (0.4, 0.0), (1.5, 0.0)],
+            &[(-0.3, 0.0), (0.8, 0.0), (1.5, 0.3)],
+        );
+
+        let detonations = field.detonate(&[0]);
+
+        assert_eq!(
+            detonations,
+            [
+                Detonation {
+                    coords: (0.0, 0.0),
+                    blast_radius: BLAST_RADIUS,
+                    kills: 1,
+                    chain_depth: 0,
+                },
+                Detonation {
+                    coords: (0.4, 0.0),
+                    blast_radius: BLAST_RADIUS,
+                    kills: 1,
+                    chain_depth: 1,
+                },
+            ]
+        );
+        assert_eq!(field.gates.len(), 1);
+        assert_eq!(field.gates[0].game_object.coords, (1.5, 0.0));
+        assert_eq!(field.enemies.len(), 1);
+        assert_eq!(field.enemies[0].game_object.coords, (1.5, 0.3));
+        assert_eq!(field.dead.len(), 2);
+    }
+
+    #[test]
+    fn blast_reaches_exactly_its_radius() {
+        let mut field = Field::new(&[(0.0, 0.0)], &[(0.5, 0.0), (0.0, -0.5), (0.5001, 0.0)]);
+
+        let detonations = field.detonate(&[0]);
+
+        assert_eq!(detonations.len(), 1);
+        assert_eq!(detonations[0].kills, 2);
+        assert_eq!(field.enemies.len(), 1);
+        assert_eq!(field.enemies[0].game_object.coords, (0.5001, 0.0));
+    }
+
+    #[test]
+    fn gate_triggered_twice_goes_off_once() {
+        let mut field = Field::new(&[(0.0, 0.0), (0.3, 0.0)], &[]);
+
+        let detonations = field.detonate(&[1, 0, 1]);
+
+        assert_eq!(
+            detonations
+                .iter()
+                .map(|d| (d.coords, d.chain_depth))
+                .collect::<Vec<_>>(),
+            [((0.3, 0.0), 0), ((0.0, 0.0), 0)]
+        );
+        assert!(field.gates.is_empty());
+    }
+}
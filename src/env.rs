@@ -0,0 +1,364 @@
+use crate::config::Config;
+use crate::constants::TICK_RATE;
+use crate::game::Game;
+use crate::mode::GameMode;
+use crate::state::GameState;
+use crate::stats::GameStats;
+
+use winit::event::VirtualKeyCode;
+
+/// Enemies beyond this many (furthest from the player first) are left out of
+/// observations.
+pub const MAX_OBSERVED_ENEMIES: usize = 64;
+pub const MAX_OBSERVED_GATES: usize = 16;
+
+const PLAYER_FEATURES: usize = 2;
+const ENEMY_FEATURES: usize = 3;
+const GATE_FEATURES: usize = 5;
+
+/// Length of an `Observation`. The layout is:
+/// - player `x, y`
+/// - `MAX_OBSERVED_ENEMIES` × enemy `x, y, present`, nearest first
+/// - `MAX_OBSERVED_GATES` × gate `x, y, cos(rotation), sin(rotation), present`,
+///   nearest first
+///
+/// Unused slots are all zeros, so `present` tells padding from an entity
+/// sitting at the origin.
+pub const OBSERVATION_SIZE: usize =
+    PLAYER_FEATURES + MAX_OBSERVED_ENEMIES * ENEMY_FEATURES + MAX_OBSERVED_GATES * GATE_FEATURES;
+
+/// Reward subtracted for every life lost.
+pub const DEATH_PENALTY: f32 = 100.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Idle,
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::Idle,
+        Action::Up,
+        Action::UpRight,
+        Action::Right,
+        Action::DownRight,
+        Action::Down,
+        Action::DownLeft,
+        Action::Left,
+        Action::UpLeft,
+    ];
+
+    /// The action at `index` in `Action::ALL`, for agents with a discrete
+    /// action head.
+    pub fn from_index(index: usize) -> Option<Action> {
+        Self::ALL.get(index).copied()
+    }
+
+    /// The movement keys `Game::update` should see held for this action.
+    pub fn keys(self) -> &'static [VirtualKeyCode] {
+        use VirtualKeyCode::{A, D, S, W};
+
+        match self {
+            Action::Idle => &[],
+            Action::Up => &[W],
+            Action::UpRight => &[W, D],
+            Action::Right => &[D],
+            Action::DownRight => &[S, D],
+            Action::Down => &[S],
+            Action::DownLeft => &[S, A],
+            Action::Left => &[A],
+            Action::UpLeft => &[W, A],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Observation(pub [f32; OBSERVATION_SIZE]);
+
+#[derive(Copy, Clone, Debug)]
+pub struct Info {
+    pub stats: GameStats,
+    pub ticks: u64,
+    /// The episode hit `Env`'s tick limit rather than ending in a game over.
+    pub truncated: bool,
+}
+
+/// A headless, gym-style wrapper around `Game` for training agents.
+pub struct Env {
+    game: Game,
+    ticks_per_step: u32,
+    max_ticks: u64,
+    ticks: u64,
+    nearest: Vec<(f32, usize)>,
+}
+
+impl Env {
+    /// Each `step` advances `ticks_per_step` fixed ticks with the chosen
+    /// action held; episodes are cut off after `max_ticks`. Every episode is
+    /// played in `mode` with `config`'s tuning.
+    pub fn new(config: Config, mode: GameMode, ticks_per_step: u32, max_ticks: u64) -> Self {
+        Self {
+            game: Game::with_config(config, mode, 0),
+            ticks_per_step: ticks_per_step.max(1),
+            max_ticks,
+            ticks: 0,
+            nearest: Vec::new(),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::with_config(self.game.config().clone(), self.game.mode().clone(), seed);
+        self.game.start();
+        self.ticks = 0;
+
+        self.observe()
+    }
+
+    /// Before the first `reset` there is no episode: nothing is simulated and
+    /// the step comes back done.
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool, Info) {
+        let dt = 1.0 / TICK_RATE;
+        let before = self.game.stats();
+
+        self.game.keys.clear();
+        self.game.keys.extend(action.keys());
+
+        for _ in 0..self.ticks_per_step {
+            if self.game.state() != GameState::Playing || self.ticks >= self.max_ticks {
+                break;
+            }
+            self.game.update(dt);
+            self.ticks += 1;
+        }
+
+        let stats = self.game.stats();
+        let reward = (stats.score - before.score) as f32
+            - (before.lives - stats.lives) as f32 * DEATH_PENALTY;
+
+        let game_over = self.game.state() != GameState::Playing;
+        let truncated = !game_over && self.ticks >= self.max_ticks;
+
+        let info = Info {
+            stats,
+            ticks: self.ticks,
+            truncated,
+        };
+
+        (self.observe(), reward, game_over || truncated, info)
+    }
+
+    fn observe(&mut self) -> Observation {
+        let mut data = [0f32; OBSERVATION_SIZE];
+        let (px, py) = self.game.player.game_object.coords;
+        let distance_sq = |(x, y): (f32, f32)| (x - px) * (x - px) + (y - py) * (y - py);
+
+        data[0] = px;
+        data[1] = py;
+        let mut offset = PLAYER_FEATURES;
+
+        self.nearest.clear();
+        self.nearest.extend(
+            self.game
+                .enemies
+                .iter()
+                .enumerate()
+                .map(|(i, enemy)| (distance_sq(enemy.game_object.coords), i)),
+        );
+        self.nearest.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (slot, &(_, i)) in self.nearest.iter().take(MAX_OBSERVED_ENEMIES).enumerate() {
+            let (x, y) = self.game.enemies[i].game_object.coords;
+            let base = offset + slot * ENEMY_FEATURES;
+            data[base..base + ENEMY_FEATURES].copy_from_slice(&[x, y, 1.0]);
+        }
+        offset += MAX_OBSERVED_ENEMIES * ENEMY_FEATURES;
+
+        self.nearest.clear();
+        self.nearest.extend(
+            self.game
+                .gates
+                .iter()
+                .enumerate()
+                .map(|(i, gate)| (distance_sq(gate.game_object.coords), i)),
+        );
+        self.nearest.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (slot, &(_, i)) in self.nearest.iter().take(MAX_OBSERVED_GATES).enumerate() {
+            let gate = &self.game.gates[i];
+            let (x, y) = gate.game_object.coords;
+            let base = offset + slot * GATE_FEATURES;
+            data[base..base + GATE_FEATURES].copy_from_slice(&[
+                x,
+                y,
+                gate.rotation.cos(),
+                gate.rotation.sin(),
+                1.0,
+            ]);
+        }
+
+        Observation(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_object::{Enemy, EnemyKind, Gate, GateMotion};
+    use crate::waves::WaveSchedule;
+
+    /// An env with no waves, so only the entities a test places are about.
+    fn quiet_env(ticks_per_step: u32, max_ticks: u64) -> Env {
+        let mode = GameMode {
+            waves: WaveSchedule {
+                waves: Vec::new(),
+                loop_from: None,
+            },
+            ..GameMode::classic()
+        };
+        let mut env = Env::new(Config::default(), mode, ticks_per_step, max_ticks);
+        env.reset(7);
+        env
+    }
+
+    fn enemy_slot(observation: &Observation, slot: usize) -> &[f32] {
+        let base = PLAYER_FEATURES + slot * ENEMY_FEATURES;
+        &observation.0[base..base + ENEMY_FEATURES]
+    }
+
+    fn gate_slot(observation: &Observation, slot: usize) -> &[f32] {
+        let base = PLAYER_FEATURES + MAX_OBSERVED_ENEMIES * ENEMY_FEATURES + slot * GATE_FEATURES;
+        &observation.0[base..base + GATE_FEATURES]
+    }
+
+    #[test]
+    fn observation_lists_nearest_first_and_pads_with_zeros() {
+        let mut env = quiet_env(1, 100);
+        env.game.player.game_object.teleport((0.1, -0.1));
+        for coords in [(0.9, -0.1), (0.3, -0.1), (0.1, 0.5)] {
+            env.game
+                .enemies
+                .push(Enemy::new(EnemyKind::Wanderer, coords));
+        }
+        let mut far = Gate::new((-0.9, -0.1), GateMotion::Static, 0.0);
+        far.rotation = 0.5;
+        env.game.gates.push(far);
+        env.game
+            .gates
+            .push(Gate::new((0.0, 0.0), GateMotion::Static, 0.0));
+
+        let observation = env.observe();
+
+        assert_eq!(&observation.0[..PLAYER_FEATURES], &[0.1, -0.1]);
+        assert_eq!(enemy_slot(&observation, 0), &[0.3, -0.1, 1.0]);
+        assert_eq!(enemy_slot(&observation, 1), &[0.1, 0.5, 1.0]);
+        assert_eq!(enemy_slot(&observation, 2), &[0.9, -0.1, 1.0]);
+        for slot in 3..MAX_OBSERVED_ENEMIES {
+            assert_eq!(enemy_slot(&observation, slot), &[0.0; ENEMY_FEATURES]);
+        }
+
+        assert_eq!(gate_slot(&observation, 0), &[0.0, 0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(
+            gate_slot(&observation, 1),
+            &[-0.9, -0.1, 0.5f32.cos(), 0.5f32.sin(), 1.0]
+        );
+        for slot in 2..MAX_OBSERVED_GATES {
+            assert_eq!(gate_slot(&observation, slot), &[0.0; GATE_FEATURES]);
+        }
+    }
+
+    #[test]
+    fn observation_keeps_only_the_nearest_enemies() {
+        let mut env = quiet_env(1, 100);
+        for i in 0..MAX_OBSERVED_ENEMIES + 4 {
+            let x = 1.5 - i as f32 * 0.02;
+            env.game
+                .enemies
+                .push(Enemy::new(EnemyKind::Wanderer, (x, 0.0)));
+        }
+
+        let observation = env.observe();
+
+        // the four furthest, at the start of the list, are left out
+        let last = enemy_slot(&observation, MAX_OBSERVED_ENEMIES - 1);
+        assert_eq!(last[0], 1.5 - 4.0 * 0.02);
+        assert_eq!(last[2], 1.0);
+    }
+
+    #[test]
+    fn reward_is_the_score_gained() {
+        let mut env = quiet_env(1, 100);
+        // the bar sits just right of the player, in the way of one tick's move
+        let radius = env.game.config().gate_radius;
+        env.game.gates.push(Gate::new(
+            (radius / 2.0 + 0.002, 0.0),
+            GateMotion::Static,
+            0.0,
+        ));
+        env.game
+            .enemies
+            .push(Enemy::new(EnemyKind::Wanderer, (0.3, 0.3)));
+
+        let (_, reward, done, info) = env.step(Action::Right);
+
+        assert!(!done);
+        assert_eq!(info.stats.kills, 1);
+        assert_eq!(reward, info.stats.score as f32);
+        assert!(reward > 0.0);
+    }
+
+    #[test]
+    fn losing_a_life_costs_the_death_penalty() {
+        let mut env = quiet_env(1, 100);
+        env.game
+            .enemies
+            .push(Enemy::new(EnemyKind::Wanderer, (0.0, 0.0)));
+
+        let (_, reward, done, info) = env.step(Action::Idle);
+
+        assert!(!done);
+        assert_eq!(info.stats.lives, Config::default().starting_lives - 1);
+        assert_eq!(reward, -DEATH_PENALTY);
+    }
+
+    #[test]
+    fn truncates_at_max_ticks() {
+        let mut env = quiet_env(4, 10);
+
+        for ticks in [4, 8] {
+            let (_, _, done, info) = env.step(Action::Idle);
+            assert!(!done);
+            assert_eq!(info.ticks, ticks);
+        }
+
+        let (_, _, done, info) = env.step(Action::Idle);
+        assert!(done);
+        assert!(info.truncated);
+        assert_eq!(info.ticks, 10);
+        assert_eq!(env.game().state(), GameState::Playing);
+    }
+
+    #[test]
+    fn step_before_reset_is_done_without_simulating() {
+        let mut env = Env::new(Config::default(), GameMode::classic(), 4, 100);
+
+        let (_, reward, done, info) = env.step(Action::Right);
+
+        assert!(done);
+        assert!(!info.truncated);
+        assert_eq!(info.ticks, 0);
+        assert_eq!(reward, 0.0);
+        assert_eq!(env.game().player.game_object.coords, (0.0, 0.0));
+    }
+}
@@ -0,0 +1,23 @@
+use crate::detonation::Detonation;
+
+/// Things that happened during a single `Game::update`, in the order they
+/// were detected. Indices refer to `Game::enemies` / `Game::gates` as they
+/// were when the event was raised.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    /// The player touched an enemy, which is fatal.
+    PlayerHitEnemy { enemy: usize },
+    /// The player touched one of the two endpoints of a gate.
+    PlayerTouchedGateEndpoint { gate: usize },
+    /// The player passed through the bar between a gate's endpoints.
+    PlayerCrossedGate { gate: usize },
+    /// A gate went off, either because the player crossed it or because it
+    /// was caught in another gate's blast.
+    GateDetonated(Detonation),
+    /// A gate reached the end of its lifetime and vanished.
+    GateExpired { coords: (f32, f32) },
+    /// The player lost a life and, if any are left, respawned.
+    PlayerDied { lives_left: u32 },
+    /// The player ran out of lives.
+    GameOver,
+}
@@ -0,0 +1,220 @@
+use crate::behaviour::rescale;
+use crate::config::Config;
+use crate::game_object::Enemy;
+use crate::spatial::SpatialGrid;
+
+/// How far an enemy looks for neighbours with `config`'s rules, or 0 if
+/// flocking is off.
+pub fn reach(config: &Config) -> f32 {
+    let mut reach = 0f32;
+    if config.separation_weight > 0.0 {
+        reach = reach.max(config.separation_radius);
+    }
+    if config.alignment_weight > 0.0 || config.cohesion_weight > 0.0 {
+        reach = reach.max(config.flock_radius);
+    }
+    reach
+}
+
+/// Blends each enemy's `desired` velocity with boids-style separation,
+/// alignment and cohesion from its neighbours, writing the result to
+/// `velocities`. Neighbours are read from positions and last-tick velocities
+/// only, so the outcome doesn't depend on the order enemies are visited in.
+/// `grid` must hold the enemies' current positions.
+pub fn flock(
+    enemies: &[Enemy],
+    grid: &SpatialGrid,
+    desired: &[(f32, f32)],
+    config: &Config,
+    velocities: &mut Vec<(f32, f32)>,
+) {
+    velocities.clear();
+
+    let separate = config.separation_weight > 0.0 && config.separation_radius > 0.0;
+    let gather = (config.alignment_weight > 0.0 || config.cohesion_weight > 0.0)
+        && config.flock_radius > 0.0;
+    if !separate && !gather {
+        velocities.extend_from_slice(desired);
+        return;
+    }
+
+    let separation_sq = config.separation_radius * config.separation_radius;
+    let flock_sq = config.flock_radius * config.flock_radius;
+    let reach = reach(config);
+    let speed = config.enemy_speed;
+
+    for (i, enemy) in enemies.iter().enumerate() {
+        let (x, y) = enemy.game_object.coords;
+        let mut push = (0f32, 0f32);
+        let mut heading = (0f32, 0f32);
+        let mut centre = (0f32, 0f32);
+        let mut flockmates = 0u32;
+
+        grid.for_each_within((x, y), reach, |j| {
+            if i == j {
+                return;
+            }
+            let (ox, oy) = grid.point(j);
+            let (dx, dy) = (x - ox, y - oy);
+            let distance_sq = dx * dx + dy * dy;
+
+            if distance_sq < separation_sq {
+                let distance = distance_sq.sqrt();
+                if distance > f32::EPSILON {
+                    // stronger the closer they are
+                    let strength = 1.0 - distance / config.separation_radius;
+                    push.0 += dx / distance * strength;
+                    push.1 += dy / distance * strength;
+                } else {
+                    // exactly stacked: split them along an axis picked by index
+                    push.0 += if i < j { 1.0 } else { -1.0 };
+                }
+            }
+            if distance_sq < flock_sq {
+                heading.0 += enemies[j].velocity.0;
+                heading.1 += enemies[j].velocity.1;
+                centre.0 += ox;
+                centre.1 += oy;
+                flockmates += 1;
+            }
+        });
+
+        let (mut vx, mut vy) = desired[i];
+        vx += config.separation_weight * speed * push.0;
+        vy += config.separation_weight * speed * push.1;
+
+        if gather && flockmates > 0 {
+            let n = flockmates as f32;
+            vx += config.alignment_weight * (heading.0 / n - enemy.velocity.0);
+            vy += config.alignment_weight * (heading.1 / n - enemy.velocity.1);
+
+            let (cx, cy) = (centre.0 / n - x, centre.1 / n - y);
+            let pull = (cx * cx + cy * cy).sqrt() / config.flock_radius;
+            let (cx, cy) = rescale((cx, cy), speed * pull.min(1.0));
+            vx += config.cohesion_weight * cx;
+            vy += config.cohesion_weight * cy;
+        }
+
+        // flocking bends the path but never speeds an enemy past its own
+        // behaviour (or a normal enemy's pace, for ones standing still)
+        let (dx, dy) = desired[i];
+        let limit = (dx * dx + dy * dy).sqrt().max(speed);
+        let length = (vx * vx + vy * vy).sqrt();
+        if length > limit {
+            vx *= limit / length;
+            vy *= limit / length;
+        }
+
+        velocities.push((vx, vy));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::Arena;
+    use crate::game_object::EnemyKind;
+
+    fn enemies_at(coords: &[(f32, f32)]) -> Vec<Enemy> {
+        coords
+            .iter()
+            .map(|&coords| Enemy::new(EnemyKind::Seeker, coords))
+            .collect()
+    }
+
+    fn run(enemies: &[Enemy], desired: &[(f32, f32)], config: &Config) -> Vec<(f32, f32)> {
+        let mut grid = SpatialGrid::new();
+        grid.rebuild(
+            &Arena::new(4.0, 4.0),
+            reach(config).max(0.1),
+            enemies.iter().map(|enemy| enemy.game_object.coords),
+        );
+        let mut velocities = Vec::new();
+        flock(enemies, &grid, desired, config, &mut velocities);
+        velocities
+    }
+
+    fn length((x, y): (f32, f32)) -> f32 {
+        (x * x + y * y).sqrt()
+    }
+
+    #[test]
+    fn splits_stacked_enemies() {
+        let config = Config::default();
+        let enemies = enemies_at(&[(0.3, 0.3), (0.3, 0.3)]);
+
+        let velocities = run(&enemies, &[(0.0, 0.0); 2], &config);
+
+        assert!(velocities[0].0 != 0.0);
+        assert_eq!(velocities[0].0, -velocities[1].0);
+    }
+
+    #[test]
+    fn zero_weights_pass_desired_through() {
+        let config = Config {
+            separation_weight: 0.0,
+            alignment_weight: 0.0,
+            cohesion_weight: 0.0,
+            ..Config::default()
+        };
+        let enemies = enemies_at(&[(0.0, 0.0), (0.01, 0.0), (0.0, 0.02)]);
+        let desired = [(0.1, 0.0), (-0.2, 0.3), (0.0, 0.0)];
+
+        assert_eq!(run(&enemies, &desired, &config), desired);
+    }
+
+    #[test]
+    fn never_speeds_an_enemy_past_its_limit() {
+        let config = Config {
+            separation_weight: 5.0,
+            alignment_weight: 1.0,
+            cohesion_weight: 1.0,
+            ..Config::default()
+        };
+        let enemies = enemies_at(&[(0.0, 0.0), (0.02, 0.0), (-0.02, 0.01), (0.0, 0.03)]);
+        let fast = 2.0 * config.enemy_speed;
+        let desired = [(0.0, 0.0), (fast, 0.0), (0.0, 0.0), (0.0, -0.1)];
+
+        let velocities = run(&enemies, &desired, &config);
+
+        for (velocity, desired) in velocities.iter().zip(desired) {
+            let limit = length(desired).max(config.enemy_speed);
+            assert!(length(*velocity) <= limit * (1.0 + 1e-5));
+        }
+        // crowded enough that the limit is what's holding them back
+        assert!(length(velocities[0]) > config.enemy_speed * 0.99);
+    }
+
+    #[test]
+    fn does_not_depend_on_visiting_order() {
+        let config = Config {
+            alignment_weight: 0.5,
+            cohesion_weight: 0.5,
+            ..Config::default()
+        };
+        let coords = [
+            (0.0, 0.0),
+            (0.05, 0.02),
+            (-0.04, 0.06),
+            (0.1, -0.08),
+            (0.2, 0.1),
+        ];
+        let mut enemies = enemies_at(&coords);
+        for (i, enemy) in enemies.iter_mut().enumerate() {
+            enemy.velocity = (0.1 * i as f32, -0.05);
+        }
+        let desired = [(0.2, 0.0), (0.0, 0.2), (-0.1, 0.1), (0.0, 0.0), (0.3, -0.1)];
+
+        let forward = run(&enemies, &desired, &config);
+
+        enemies.reverse();
+        let mut reversed_desired = desired;
+        reversed_desired.reverse();
+        let mut backward = run(&enemies, &reversed_desired, &config);
+        backward.reverse();
+
+        for (a, b) in forward.iter().zip(&backward) {
+            assert!((a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6);
+        }
+    }
+}
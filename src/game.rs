@@ -1,48 +1,234 @@
-use crate::constants::{
-    ENEMY_BUFFER, ENEMY_SPAWN_FREQ, ENEMY_SPEED, GATE_SPAWN_FREQ, PLAYER_SPEED,
-};
-use crate::game_object::{Enemy, Gate, Player};
+use crate::behaviour::{rescale, Surroundings};
+use crate::collision;
+use crate::config::Config;
+use crate::detonation;
+use crate::event::GameEvent;
+use crate::flocking;
+use crate::game_object::{Enemy, Gate, GateMotion, Player};
+use crate::mode::GameMode;
+use crate::spatial::SpatialGrid;
+use crate::sprite::Sprite;
+use crate::state::GameState;
+use crate::stats::GameStats;
+use crate::waves::{Spawn, WaveDirector};
 
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
 use std::collections::HashSet;
-use std::f32::EPSILON;
+use std::f32::consts::TAU;
+use std::iter;
 use winit::event::VirtualKeyCode;
 
-fn rescale(v: (f32, f32), speed: f32) -> (f32, f32) {
-    let d = (v.0 * v.0 + v.1 * v.1).sqrt();
-    (v.0 * speed / (d + EPSILON), v.1 * speed / (d + EPSILON))
-}
-
 pub struct Game {
-    paused: bool,
+    state: GameState,
+    config: Config,
+    mode: GameMode,
+    seed: u64,
+    /// Whether restarts reuse `seed` rather than rolling a new one.
+    fixed_seed: bool,
+    rng: StdRng,
     timer: f32,
-    last_enemy_time: f32,
     last_gate_time: f32,
     pub keys: HashSet<VirtualKeyCode>,
-    enemies_per_wave: u32,
+    director: WaveDirector,
+
+    score: u64,
+    multiplier: u32,
+    lives: u32,
+    kills: u32,
+    gates_detonated: u32,
 
     pub player: Player,
     pub enemies: Vec<Enemy>,
     pub gates: Vec<Gate>,
+
+    events: Vec<GameEvent>,
+    /// Per-tick scratch space for enemy steering, kept to avoid allocating.
+    desired: Vec<(f32, f32)>,
+    velocities: Vec<(f32, f32)>,
+    /// Where the enemies and gates are, rebuilt whenever they move.
+    enemy_grid: SpatialGrid,
+    gate_grid: SpatialGrid,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Game {
+    /// A game with a random seed; every restart rolls a new one.
     pub fn new() -> Self {
+        Self::with_random_seed(Config::default(), GameMode::default())
+    }
+
+    pub fn with_random_seed(config: Config, mode: GameMode) -> Self {
+        Self {
+            fixed_seed: false,
+            ..Self::with_config(config, mode, thread_rng().gen())
+        }
+    }
+
+    /// A game whose runs all play out from `seed`, so identical input
+    /// reproduces them exactly.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_mode(GameMode::default(), seed)
+    }
+
+    pub fn with_mode(mode: GameMode, seed: u64) -> Self {
+        Self::with_config(Config::default(), mode, seed)
+    }
+
+    pub fn with_config(config: Config, mode: GameMode, seed: u64) -> Self {
+        log::info!("mode: {}, seed: {}", mode.name, seed);
+        let director = WaveDirector::new(mode.waves.clone());
+        let lives = config.starting_lives;
+
         Self {
-            paused: false,
+            state: GameState::Title,
+            config,
+            mode,
+            seed,
+            fixed_seed: true,
+            rng: StdRng::seed_from_u64(seed),
             timer: 0f32,
-            last_enemy_time: 0f32,
             last_gate_time: 0f32,
             keys: HashSet::new(),
-            enemies_per_wave: 1,
+            director,
+            score: 0,
+            multiplier: 1,
+            lives,
+            kills: 0,
+            gates_detonated: 0,
             player: Player::new(),
             enemies: Vec::new(),
             gates: Vec::new(),
+            events: Vec::new(),
+            desired: Vec::new(),
+            velocities: Vec::new(),
+            enemy_grid: SpatialGrid::new(),
+            gate_grid: SpatialGrid::new(),
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Swaps in new tuning values mid-run. Values only read at the start of
+    /// a run, like `starting_lives`, apply from the next one.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    pub fn mode(&self) -> &GameMode {
+        &self.mode
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    /// Feeds a key press to the state machine.
+    pub fn key_pressed(&mut self, key: VirtualKeyCode) {
+        if let Some(next) = self.state.on_key(key) {
+            self.set_state(next);
+        }
+    }
+
+    pub fn set_state(&mut self, next: GameState) {
+        let prev = self.state;
+        self.exit_state(prev);
+        self.state = next;
+        self.enter_state(prev, next);
+    }
+
+    /// Shorthand for leaving the title screen and starting a fresh run.
+    pub fn start(&mut self) {
+        self.set_state(GameState::Playing);
+    }
+
+    fn exit_state(&mut self, state: GameState) {
+        if state == GameState::Playing {
+            log::debug!("leaving play at {:.2}s: {:?}", self.timer, self.stats());
+        }
+    }
+
+    fn enter_state(&mut self, prev: GameState, state: GameState) {
+        log::info!("{:?} -> {:?}", prev, state);
+
+        match state {
+            GameState::Title => self.reset(),
+            GameState::Playing if prev != GameState::Paused => self.reset(),
+            GameState::GameOver => log::info!("game over: {:?}", self.stats()),
+            _ => {}
+        }
+    }
+
+    /// Puts the run back to how `Game::with_seed` left it, keeping the
+    /// current state and held keys.
+    fn reset(&mut self) {
+        let seed = if self.fixed_seed {
+            self.seed
+        } else {
+            self.rng.gen()
+        };
+        let keys = std::mem::take(&mut self.keys);
+        let config = self.config.clone();
+        let mode = self.mode.clone();
+
+        *self = Self {
+            state: self.state,
+            fixed_seed: self.fixed_seed,
+            keys,
+            ..Self::with_config(config, mode, seed)
+        };
+    }
+
+    pub fn stats(&self) -> GameStats {
+        GameStats {
+            score: self.score,
+            multiplier: self.multiplier,
+            lives: self.lives,
+            kills: self.kills,
+            gates_detonated: self.gates_detonated,
+            time: self.timer,
+            invulnerable: self.player.invulnerable_time > 0.0,
         }
     }
 
+    /// Everything to draw, in no particular order.
+    pub fn sprites(&self) -> impl Iterator<Item = &dyn Sprite> {
+        iter::once(&self.player as &dyn Sprite)
+            .chain(self.enemies.iter().map(|enemy| enemy as &dyn Sprite))
+            .chain(self.gates.iter().map(|gate| gate as &dyn Sprite))
+    }
+
+    /// Events raised by the most recent call to `update`.
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
     pub fn update(&mut self, dt: f32) {
-        if !self.paused {
+        self.events.clear();
+
+        // snapshot positions even when not simulating, so interpolation
+        // settles instead of replaying the last tick while paused
+        self.player.game_object.prev_coords = self.player.game_object.coords;
+        for enemy in self.enemies.iter_mut() {
+            enemy.game_object.prev_coords = enemy.game_object.coords;
+        }
+        for gate in self.gates.iter_mut() {
+            gate.game_object.prev_coords = gate.game_object.coords;
+            gate.prev_rotation = gate.rotation;
+        }
+
+        if self.state.simulates() {
             // move player
             let mut dx = 0f32;
             let mut dy = 0f32;
@@ -65,108 +251,278 @@ impl Game {
                 }
             }
 
-            #[cfg(target_arch = "wasm32")]
-            let sv = rescale((dx, dy), PLAYER_SPEED * 0.5);
-            #[cfg(not(target_arch = "wasm32"))]
-            let sv = rescale((dx, dy), PLAYER_SPEED);
+            let sv = rescale((dx, dy), self.config.player_speed * dt);
 
-            self.player.game_object.coords.0 += sv.0;
-            self.player.game_object.coords.1 += sv.1;
+            let player = &mut self.player.game_object;
+            player.coords = self.mode.arena.clamp(
+                (player.coords.0 + sv.0, player.coords.1 + sv.1),
+                self.config.player_radius,
+            );
 
-            // nmove enemies
+            // move enemies
+            self.rebuild_enemy_grid();
+            self.rebuild_gate_grid();
+            let world = Surroundings {
+                player: self.player.game_object.coords,
+                gates: &self.gates,
+                gate_grid: &self.gate_grid,
+                arena: &self.mode.arena,
+                config: &self.config,
+                dt,
+            };
+            self.desired.clear();
             for enemy in self.enemies.iter_mut() {
-                let (dx, dy) = (
-                    self.player.game_object.coords.0 - enemy.game_object.coords.0,
-                    self.player.game_object.coords.1 - enemy.game_object.coords.1,
-                );
+                let velocity = enemy
+                    .behaviour
+                    .steer(&enemy.game_object, &world, &mut self.rng);
+                self.desired.push(velocity);
+            }
+            flocking::flock(
+                &self.enemies,
+                &self.enemy_grid,
+                &self.desired,
+                &self.config,
+                &mut self.velocities,
+            );
+
+            for (enemy, &velocity) in self.enemies.iter_mut().zip(self.velocities.iter()) {
+                enemy.age += dt;
+                enemy.velocity = velocity;
 
-                #[cfg(target_arch = "wasm32")]
-                let sv = rescale((dx, dy), ENEMY_SPEED * 0.5);
-                #[cfg(not(target_arch = "wasm32"))]
-                let sv = rescale((dx, dy), ENEMY_SPEED);
-                enemy.game_object.coords.0 += sv.0;
-                enemy.game_object.coords.1 += sv.1;
+                let coords = &mut enemy.game_object.coords;
+                *coords = world.arena.clamp(
+                    (coords.0 + velocity.0 * dt, coords.1 + velocity.1 * dt),
+                    self.config.enemy_radius,
+                );
             }
 
             // move gates
             for gate in self.gates.iter_mut() {
-                gate.rotation += dt * gate.spin_speed;
+                gate.advance(dt, &self.mode.arena, self.config.gate_radius);
+            }
+
+            self.rebuild_enemy_grid();
+            self.rebuild_gate_grid();
+            collision::detect(
+                &self.config,
+                &self.player,
+                &self.enemy_grid,
+                &self.gates,
+                &self.gate_grid,
+                &mut self.events,
+            );
 
-                // println!("dt={}, gate.spin_speed={}", dt, gate.spin_speed);
-                // println!("gate rot: {}", gate.rotation);
+            self.player.invulnerable_time = (self.player.invulnerable_time - dt).max(0.0);
+
+            let hit = self
+                .events
+                .iter()
+                .any(|event| matches!(event, GameEvent::PlayerHitEnemy { .. }));
+
+            if hit && self.player.invulnerable_time <= 0.0 {
+                self.kill_player();
+                // the run ended this tick; nothing else should happen in it
+                if self.state != GameState::Playing {
+                    return;
+                }
+            } else {
+                self.detonate_crossed_gates();
             }
+            self.expire_gates();
 
             self.timer += dt;
 
-            if self.timer > self.last_enemy_time + ENEMY_SPAWN_FREQ {
-                println!("spawn enemy");
-                self.spawn_enemies();
+            while let Some(spawn) = self.director.poll(self.timer) {
+                log::debug!("spawn {:?}", spawn);
+                self.spawn_enemies(spawn);
             }
 
-            if self.timer > self.last_gate_time + GATE_SPAWN_FREQ {
-                println!("spawn gate");
+            if self.timer > self.last_gate_time + self.config.gate_spawn_freq {
+                log::debug!("spawn gate");
                 self.spawn_gate();
             }
         }
     }
 
-    pub fn toggle_pause(&mut self) {
-        self.paused = !self.paused;
+    fn rebuild_enemy_grid(&mut self) {
+        // sized for the flocking and collision queries made every tick
+        let cell_size = (self.config.player_radius + self.config.enemy_radius)
+            .max(flocking::reach(&self.config));
+        self.enemy_grid.rebuild(
+            &self.mode.arena,
+            cell_size,
+            self.enemies.iter().map(|enemy| enemy.game_object.coords),
+        );
     }
 
-    fn spawn_enemies(&mut self) {
-        let mut rng = thread_rng();
+    fn rebuild_gate_grid(&mut self) {
+        let cell_size = self.config.gate_radius.max(self.config.gate_blast_radius);
+        self.gate_grid.rebuild(
+            &self.mode.arena,
+            cell_size,
+            self.gates.iter().map(|gate| gate.game_object.coords),
+        );
+    }
 
-        let quadrant = rng.gen_range(1..5);
-        let x_min;
-        let x_max;
-        let y_min;
-        let y_max;
+    fn detonate_crossed_gates(&mut self) {
+        let crossed = self
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::PlayerCrossedGate { gate } => Some(*gate),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
 
-        match quadrant {
-            1 => {
-                x_min = 1f32 - 2f32 * ENEMY_BUFFER;
-                x_max = 1f32;
-                y_min = 1f32 - 2f32 * ENEMY_BUFFER;
-                y_max = 1f32;
-            }
-            2 => {
-                x_min = -1f32;
-                x_max = -1f32 + 2f32 * ENEMY_BUFFER;
-                y_min = 1f32 - 2f32 * ENEMY_BUFFER;
-                y_max = 1f32;
-            }
-            3 => {
-                x_min = -1f32;
-                x_max = -1f32 + 2f32 * ENEMY_BUFFER;
-                y_min = -1f32;
-                y_max = -1f32 + 2f32 * ENEMY_BUFFER;
-            }
-            _ => {
-                x_min = 1f32 - 2f32 * ENEMY_BUFFER;
-                x_max = 1f32;
-                y_min = -1f32;
-                y_max = -1f32 + 2f32 * ENEMY_BUFFER;
-            }
+        if crossed.is_empty() {
+            return;
         }
 
-        for _ in 0..self.enemies_per_wave {
-            let x = rng.gen_range(x_min..x_max);
-            let y = rng.gen_range(y_min..y_max);
-            self.enemies.push(Enemy::new((x, y)));
+        let mut dead = Vec::new();
+        let detonations = detonation::detonate(
+            &crossed,
+            &mut self.gates,
+            &self.gate_grid,
+            &mut self.enemies,
+            &self.enemy_grid,
+            &mut dead,
+            self.config.gate_blast_radius,
+        );
+
+        for enemy in dead {
+            let children = enemy.behaviour.on_death(
+                &enemy.game_object,
+                &self.mode.arena,
+                &self.config,
+                &mut self.rng,
+            );
+            self.enemies.extend(children);
         }
 
-        self.enemies_per_wave += 1;
-        self.last_enemy_time = self.timer;
+        for detonation in detonations {
+            self.multiplier = self.multiplier.saturating_add(1);
+            self.gates_detonated += 1;
+            self.kills += detonation.kills;
+            let points = (detonation.kills as u64)
+                .saturating_mul(self.config.enemy_points)
+                .saturating_mul(self.multiplier as u64);
+            self.score = self.score.saturating_add(points);
+            self.events.push(GameEvent::GateDetonated(detonation));
+        }
     }
 
-    fn spawn_gate(&mut self) {
-        let mut rng = thread_rng();
+    fn kill_player(&mut self) {
+        self.lives -= 1;
+        self.multiplier = 1;
+        self.events.push(GameEvent::PlayerDied {
+            lives_left: self.lives,
+        });
+
+        if self.lives == 0 {
+            self.events.push(GameEvent::GameOver);
+            self.set_state(GameState::GameOver);
+        } else {
+            self.player.game_object.teleport((0.0, 0.0));
+            self.player.invulnerable_time = self.config.respawn_invulnerability;
+        }
+    }
+
+    fn spawn_enemies(&mut self, spawn: Spawn) {
+        let positions = spawn.pattern.positions(
+            spawn.count,
+            &self.mode.arena,
+            self.config.enemy_radius,
+            self.player.game_object.coords,
+            &mut self.rng,
+        );
+
+        self.enemies.extend(
+            positions
+                .into_iter()
+                .map(|coords| Enemy::new(spawn.enemy, coords)),
+        );
+    }
+
+    fn expire_gates(&mut self) {
+        let lifetime = self.config.gate_lifetime;
+        for gate in self.gates.iter().filter(|gate| gate.expired(lifetime)) {
+            self.events.push(GameEvent::GateExpired {
+                coords: gate.game_object.coords,
+            });
+        }
+        self.gates.retain(|gate| !gate.expired(lifetime));
+    }
+
+    /// A gate somewhere in the arena with a random motion and spin.
+    fn sample_gate(&mut self) -> Gate {
+        let config = &self.config;
+        let rng = &mut self.rng;
 
-        let x = rng.gen_range(0.0..1.0);
-        let y = rng.gen_range(0.0..1.0);
-        self.gates.push(Gate::new((x, y)));
+        let spin = rng.gen_range(config.gate_min_spin..=config.gate_max_spin);
+        let spin = if rng.gen_bool(0.5) { spin } else { -spin };
+
+        let roll = rng.gen::<f32>();
+        if roll < config.gate_drift_chance {
+            let coords = self.mode.arena.sample(rng, config.gate_radius);
+            let angle = rng.gen_range(0.0..TAU);
+            let velocity = (
+                config.gate_drift_speed * angle.cos(),
+                config.gate_drift_speed * angle.sin(),
+            );
+            Gate::new(coords, GateMotion::Drift { velocity }, spin)
+        } else if roll < config.gate_drift_chance + config.gate_orbit_chance {
+            // keep the whole orbit inside the walls
+            let radius = config.gate_orbit_radius;
+            let center = self.mode.arena.sample(rng, config.gate_radius + radius);
+            let speed = if rng.gen_bool(0.5) {
+                config.gate_orbit_speed
+            } else {
+                -config.gate_orbit_speed
+            };
+            let motion = GateMotion::Orbit {
+                center,
+                radius,
+                phase: rng.gen_range(0.0..TAU),
+                angular_speed: speed,
+            };
+            Gate::new(center, motion, spin)
+        } else {
+            let coords = self.mode.arena.sample(rng, config.gate_radius);
+            Gate::new(coords, GateMotion::Static, spin)
+        }
+    }
+
+    /// Places a gate somewhere clear of the player and the other gates. If
+    /// the field is full, or no clear spot turns up, this spawn is skipped.
+    fn spawn_gate(&mut self) {
+        const ATTEMPTS: u32 = 16;
 
         self.last_gate_time = self.timer;
+        if self.gates.len() >= self.config.max_gates as usize {
+            log::debug!("gate cap reached");
+            return;
+        }
+
+        let player = self.player.game_object.coords;
+        let clear = |coords: (f32, f32), other: (f32, f32), distance: f32| {
+            let (dx, dy) = (coords.0 - other.0, coords.1 - other.1);
+            dx * dx + dy * dy >= distance * distance
+        };
+
+        for _ in 0..ATTEMPTS {
+            let gate = self.sample_gate();
+            let coords = gate.game_object.coords;
+
+            if clear(coords, player, self.config.gate_spawn_clearance)
+                && self
+                    .gates
+                    .iter()
+                    .all(|gate| clear(coords, gate.game_object.coords, self.config.gate_spacing))
+            {
+                self.gates.push(gate);
+                return;
+            }
+        }
+        log::debug!("no room for a gate");
     }
 }
@@ -1,8 +1,29 @@
 use std::f32::consts::PI;
 
-use crate::constants::{ENEMY_RADIUS, GATE_RADIUS, NUM_TEXTURES, PLAYER_RADIUS};
+use crate::arena::Arena;
+use crate::behaviour::{self, Behaviour};
+use crate::config::Config;
+use crate::constants::NUM_TEXTURES;
+use crate::mesh::{Mesh, MeshId};
 use crate::sprite::{Instance, Sprite, Vertex};
 
+use serde::{Deserialize, Serialize};
+
+/// Opacity of a gate in the instant before it expires.
+const EXPIRING_GATE_OPACITY: f32 = 0.25;
+/// Seconds a freshly spawned enemy takes to grow to full size.
+const SPAWN_IN_TIME: f32 = 0.3;
+/// How often the player flickers while invulnerable.
+const INVULNERABLE_FLASHES_PER_SECOND: f32 = 8.0;
+
+/// `point` relative to `origin`, rotated by `rotation`; the inverse of the
+/// shader's instance transform.
+fn to_frame(point: (f32, f32), origin: (f32, f32), rotation: f32) -> (f32, f32) {
+    let (dx, dy) = (point.0 - origin.0, point.1 - origin.1);
+    let (sin, cos) = rotation.sin_cos();
+    (dx * cos - dy * sin, dx * sin + dy * cos)
+}
+
 fn t(x: f32, texture_index: f32) -> f32 {
     let x_min = texture_index / (NUM_TEXTURES as f32);
     let x_max = (texture_index + 1f32) / (NUM_TEXTURES as f32);
@@ -11,54 +32,272 @@ fn t(x: f32, texture_index: f32) -> f32 {
 
 pub struct GameObject {
     pub coords: (f32, f32),
+    /// Where the object was at the start of the current update.
+    pub prev_coords: (f32, f32),
+}
+
+impl GameObject {
+    pub fn new(coords: (f32, f32)) -> Self {
+        Self {
+            coords,
+            prev_coords: coords,
+        }
+    }
+
+    /// Position to draw at, `alpha` of the way from the previous update.
+    pub fn lerp(&self, alpha: f32) -> (f32, f32) {
+        (
+            self.prev_coords.0 + (self.coords.0 - self.prev_coords.0) * alpha,
+            self.prev_coords.1 + (self.coords.1 - self.prev_coords.1) * alpha,
+        )
+    }
+
+    /// Moves the object without it counting as movement, e.g. for respawns.
+    pub fn teleport(&mut self, coords: (f32, f32)) {
+        self.coords = coords;
+        self.prev_coords = coords;
+    }
 }
 
 pub struct Player {
     pub game_object: GameObject,
+    /// Seconds of respawn protection left.
+    pub invulnerable_time: f32,
+}
+
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum EnemyKind {
+    /// Homes straight in on the player.
+    #[default]
+    Seeker,
+    /// Drifts about at random.
+    Wanderer,
+    /// Winds up, then dashes at the player.
+    Charger,
+    /// Splits in two when killed.
+    Splitter,
+    /// Chases the player but steers clear of gates.
+    GateAvoider,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 5] = [
+        EnemyKind::Seeker,
+        EnemyKind::Wanderer,
+        EnemyKind::Charger,
+        EnemyKind::Splitter,
+        EnemyKind::GateAvoider,
+    ];
+
+    /// Which image in the texture atlas the kind is drawn with.
+    pub fn texture_index(self) -> usize {
+        match self {
+            EnemyKind::Seeker => 1,
+            EnemyKind::Wanderer => 3,
+            EnemyKind::Charger => 4,
+            EnemyKind::Splitter => 5,
+            EnemyKind::GateAvoider => 6,
+        }
+    }
+
+    /// Colour the kind's texture is multiplied by, so kinds can be told
+    /// apart at a glance.
+    pub fn tint(self) -> [f32; 4] {
+        match self {
+            EnemyKind::Seeker => [1.0, 1.0, 1.0, 1.0],
+            EnemyKind::Wanderer => [0.6, 1.0, 0.6, 1.0],
+            EnemyKind::Charger => [1.0, 0.6, 0.3, 1.0],
+            EnemyKind::Splitter => [0.8, 0.5, 1.0, 1.0],
+            EnemyKind::GateAvoider => [0.4, 0.9, 1.0, 1.0],
+        }
+    }
 }
 
 pub struct Enemy {
+    pub kind: EnemyKind,
     pub game_object: GameObject,
+    /// World units per second, as steered on the last update.
+    pub velocity: (f32, f32),
+    pub behaviour: Box<dyn Behaviour>,
+    /// Seconds since the enemy spawned.
+    pub age: f32,
+}
+
+/// How a gate moves about, picked when it spawns.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GateMotion {
+    Static,
+    /// Straight line at `velocity` units per second, bouncing off walls.
+    Drift {
+        velocity: (f32, f32),
+    },
+    /// Circles `center`, starting at angle `phase`.
+    Orbit {
+        center: (f32, f32),
+        radius: f32,
+        phase: f32,
+        /// Radians per second; negative goes clockwise.
+        angular_speed: f32,
+    },
+}
+
+impl GateMotion {
+    /// Where an orbit puts its gate when it's at `angle`.
+    fn orbit_position(center: (f32, f32), radius: f32, angle: f32) -> (f32, f32) {
+        (
+            center.0 + radius * angle.cos(),
+            center.1 + radius * angle.sin(),
+        )
+    }
 }
 
 pub struct Gate {
     pub game_object: GameObject,
     pub rotation: f32,
+    pub prev_rotation: f32,
     pub spin_speed: f32,
+    pub motion: GateMotion,
+    /// Seconds since the gate spawned.
+    pub age: f32,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Player {
     pub fn new() -> Self {
         Self {
-            game_object: GameObject { coords: (0.0, 0.0) },
+            game_object: GameObject::new((0.0, 0.0)),
+            invulnerable_time: 0.0,
         }
     }
 }
 
 impl Enemy {
-    pub fn new(coords: (f32, f32)) -> Self {
+    pub fn new(kind: EnemyKind, coords: (f32, f32)) -> Self {
+        Self::with_behaviour(kind, coords, behaviour::for_kind(kind))
+    }
+
+    pub fn with_behaviour(
+        kind: EnemyKind,
+        coords: (f32, f32),
+        behaviour: Box<dyn Behaviour>,
+    ) -> Self {
         Self {
-            game_object: GameObject { coords },
+            kind,
+            game_object: GameObject::new(coords),
+            velocity: (0.0, 0.0),
+            behaviour,
+            age: 0.0,
         }
     }
 }
 
 impl Gate {
-    pub fn new(coords: (f32, f32)) -> Self {
+    /// An orbiting gate starts on its orbit whatever `coords` says.
+    pub fn new(coords: (f32, f32), motion: GateMotion, spin_speed: f32) -> Self {
+        let coords = match motion {
+            GateMotion::Orbit {
+                center,
+                radius,
+                phase,
+                ..
+            } => GateMotion::orbit_position(center, radius, phase),
+            _ => coords,
+        };
+
         Self {
-            game_object: GameObject { coords },
+            game_object: GameObject::new(coords),
             rotation: 0.0,
-            spin_speed: 1.0,
+            prev_rotation: 0.0,
+            spin_speed,
+            motion,
+            age: 0.0,
         }
     }
+
+    /// Ages, spins and moves the gate by one tick. Drifting gates bounce so
+    /// their bar stays inside `arena`.
+    pub fn advance(&mut self, dt: f32, arena: &Arena, radius: f32) {
+        self.age += dt;
+        self.rotation += dt * self.spin_speed;
+
+        let coords = &mut self.game_object.coords;
+        match &mut self.motion {
+            GateMotion::Static => {}
+            GateMotion::Drift { velocity } => {
+                let next = (coords.0 + velocity.0 * dt, coords.1 + velocity.1 * dt);
+                let clamped = arena.clamp(next, radius);
+                if clamped.0 != next.0 {
+                    velocity.0 = -velocity.0;
+                }
+                if clamped.1 != next.1 {
+                    velocity.1 = -velocity.1;
+                }
+                *coords = clamped;
+            }
+            GateMotion::Orbit {
+                center,
+                radius: orbit_radius,
+                phase,
+                angular_speed,
+            } => {
+                let angle = *phase + *angular_speed * self.age;
+                *coords = GateMotion::orbit_position(*center, *orbit_radius, angle);
+            }
+        }
+    }
+
+    pub fn expired(&self, lifetime: f32) -> bool {
+        self.age >= lifetime
+    }
+
+    /// How much of the gate is left to see: 1 until the last `warning_time`
+    /// seconds of its lifetime, then falling to 0 as it expires.
+    pub fn fade(&self, lifetime: f32, warning_time: f32) -> f32 {
+        if warning_time <= 0.0 {
+            return 1.0;
+        }
+        ((lifetime - self.age) / warning_time).clamp(0.0, 1.0)
+    }
+
+    /// Corner `k` of the drawn triangle in the gate's own frame, for a gate
+    /// of `radius`: 0 is A, its point, and 1 and 2 are B and C.
+    fn corner(radius: f32, k: u32) -> (f32, f32) {
+        let angle = k as f32 * 2.0 * PI / 3.0;
+        (radius * angle.cos(), radius * angle.sin())
+    }
+
+    /// The two ends of the gate's bar in its own frame: corners B and C, so
+    /// the bar is the triangle's back edge as drawn.
+    pub fn local_endpoints(radius: f32) -> ((f32, f32), (f32, f32)) {
+        (Self::corner(radius, 1), Self::corner(radius, 2))
+    }
+
+    /// `point` in the gate's own frame as of the current update, undoing the
+    /// translation and rotation the shader applies.
+    pub fn to_local(&self, point: (f32, f32)) -> (f32, f32) {
+        to_frame(point, self.game_object.coords, self.rotation)
+    }
+
+    /// `point` in the gate's own frame as of the previous update.
+    pub fn to_prev_local(&self, point: (f32, f32)) -> (f32, f32) {
+        to_frame(point, self.game_object.prev_coords, self.prev_rotation)
+    }
 }
 
 impl Sprite for Player {
-    fn get_vertices() -> Vec<Vertex> {
-        let r = PLAYER_RADIUS;
+    fn meshes(config: &Config) -> Vec<(MeshId, Mesh)> {
+        let r = config.player_radius;
         let i = 0f32;
 
-        vec![
+        let vertices = vec![
             Vertex {
                 position: [-r, -r, 0.0],
                 tex_coords: [t(0.0, i), 1.0],
@@ -77,17 +316,28 @@ impl Sprite for Player {
                 position: [-r, r, 0.0],
                 tex_coords: [t(0.0, i), 0.0],
             }, // D
-        ]
+        ];
+
+        vec![(
+            MeshId::Player,
+            Mesh {
+                vertices,
+                indices: vec![0, 1, 2, 0, 2, 3],
+            },
+        )]
     }
 
-    fn get_indices() -> &'static [u16] {
-        &[0, 1, 2, 0, 2, 3]
+    fn mesh(&self) -> MeshId {
+        MeshId::Player
     }
 
-    fn get_instance(&self) -> Instance {
+    fn get_instance(&self, alpha: f32, _config: &Config) -> Instance {
+        let (x, y) = self.game_object.lerp(alpha);
+        let flash = self.invulnerable_time > 0.0
+            && (self.invulnerable_time * INVULNERABLE_FLASHES_PER_SECOND).fract() < 0.5;
         Instance {
-            instance_pos: [self.game_object.coords.0, self.game_object.coords.1, 0.0],
-            theta: 0.0,
+            opacity: if flash { 0.3 } else { 1.0 },
+            ..Instance::new(x, y, 0.0)
         }
     }
 }
@@ -101,38 +351,58 @@ impl Sprite for Enemy {
     /// [0, 0]      [1, 0]
     ///
     /// [0, 1]      [1, 1]
-    fn get_vertices() -> Vec<Vertex> {
-        let r = ENEMY_RADIUS;
-        let i = 1f32;
+    ///
+    /// One mesh per kind, each textured with the kind's atlas region.
+    fn meshes(config: &Config) -> Vec<(MeshId, Mesh)> {
+        let r = config.enemy_radius;
+        let diamond = |i: f32| {
+            vec![
+                Vertex {
+                    position: [-r, 0.0, 0.0],
+                    tex_coords: [t(0.0, i), 0.5],
+                }, // A
+                Vertex {
+                    position: [0.0, -r, 0.0],
+                    tex_coords: [t(0.5, i), 1.0],
+                }, // B
+                Vertex {
+                    position: [r, 0.0, 0.0],
+                    tex_coords: [t(1.0, i), 0.5],
+                }, // C
+                Vertex {
+                    position: [0.0, r, 0.0],
+                    tex_coords: [t(0.5, i), 0.0],
+                }, // D
+            ]
+        };
 
-        vec![
-            Vertex {
-                position: [-r, 0.0, 0.0],
-                tex_coords: [t(0.0, i), 0.5],
-            }, // A
-            Vertex {
-                position: [0.0, -r, 0.0],
-                tex_coords: [t(0.5, i), 1.0],
-            }, // B
-            Vertex {
-                position: [r, 0.0, 0.0],
-                tex_coords: [t(1.0, i), 0.5],
-            }, // C
-            Vertex {
-                position: [0.0, r, 0.0],
-                tex_coords: [t(0.5, i), 0.0],
-            }, // D
-        ]
+        EnemyKind::ALL
+            .into_iter()
+            .map(|kind| {
+                (
+                    MeshId::Enemy(kind),
+                    Mesh {
+                        vertices: diamond(kind.texture_index() as f32),
+                        indices: vec![0, 1, 2, 0, 2, 3],
+                    },
+                )
+            })
+            .collect()
     }
 
-    fn get_indices() -> &'static [u16] {
-        &[0, 1, 2, 0, 2, 3]
+    fn mesh(&self) -> MeshId {
+        MeshId::Enemy(self.kind)
     }
 
-    fn get_instance(&self) -> Instance {
+    fn get_instance(&self, alpha: f32, _config: &Config) -> Instance {
+        let (x, y) = self.game_object.lerp(alpha);
+        // ease out of nothing over the first moments after spawning
+        let grown = (self.age / SPAWN_IN_TIME).min(1.0);
+        let size = grown * (2.0 - grown);
         Instance {
-            instance_pos: [self.game_object.coords.0, self.game_object.coords.1, 0.0],
-            theta: 0.0,
+            scale: [size, size],
+            tint: self.kind.tint(),
+            ..Instance::new(x, y, self.behaviour.rotation())
         }
     }
 }
@@ -146,36 +416,50 @@ impl Sprite for Gate {
     /// [0,0]    [1,0]
     ///
     /// [0,1]    [1,1]
-    fn get_vertices() -> Vec<Vertex> {
-        let r = GATE_RADIUS;
-        let dt = 2f32 * PI / 3f32;
+    fn meshes(config: &Config) -> Vec<(MeshId, Mesh)> {
+        let r = config.gate_radius;
         let i = 2f32;
+        let corner = |k| {
+            let (x, y) = Gate::corner(r, k);
+            [x, y, 0.0]
+        };
 
-        vec![
+        let vertices = vec![
             Vertex {
-                position: [r * 0f32.cos(), r * 0f32.sin(), 0.0],
+                position: corner(0),
                 tex_coords: [t(0.5, i), 0.0],
             }, // A
             Vertex {
-                position: [r * dt.cos(), r * dt.sin(), 0.0],
+                position: corner(1),
                 tex_coords: [t(0.0, i), 1.0],
             }, // B
             Vertex {
-                position: [r * (2f32 * dt).cos(), r * (2f32 * dt).sin(), 0.0],
+                position: corner(2),
                 tex_coords: [t(1.0, i), 1.0],
+            }, // C
+        ];
+
+        vec![(
+            MeshId::Gate,
+            Mesh {
+                vertices,
+                indices: vec![0, 1, 2],
             },
-        ]
+        )]
     }
 
-    fn get_indices() -> &'static [u16] {
-        &[0, 1, 2, /* pad */ 0]
+    fn mesh(&self) -> MeshId {
+        MeshId::Gate
     }
 
-    fn get_instance(&self) -> Instance {
+    fn get_instance(&self, alpha: f32, config: &Config) -> Instance {
+        let (x, y) = self.game_object.lerp(alpha);
+        let theta = self.prev_rotation + (self.rotation - self.prev_rotation) * alpha;
+        // expiring gates fade but never vanish, so they can still be aimed at
+        let fade = self.fade(config.gate_lifetime, config.gate_warning_time);
         Instance {
-            instance_pos: [self.game_object.coords.0, self.game_object.coords.1, 0.0],
-            theta: self.rotation,
-            // theta: 0.0,
+            opacity: EXPIRING_GATE_OPACITY + (1.0 - EXPIRING_GATE_OPACITY) * fade,
+            ..Instance::new(x, y, theta)
         }
     }
 }
@@ -0,0 +1,71 @@
+/// A GPU buffer that reallocates itself, doubling in size, whenever a write
+/// wouldn't fit.
+pub struct GrowableBuffer {
+    buffer: wgpu::Buffer,
+    capacity: u64,
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+}
+
+impl GrowableBuffer {
+    /// `usage` gets `COPY_DST` added, since the buffer is filled by writes.
+    pub fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        capacity: u64,
+    ) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        let capacity = capacity.max(wgpu::COPY_BUFFER_ALIGNMENT);
+
+        Self {
+            buffer: Self::allocate(device, label, usage, capacity),
+            capacity,
+            label,
+            usage,
+        }
+    }
+
+    fn allocate(
+        device: &wgpu::Device,
+        label: &str,
+        usage: wgpu::BufferUsages,
+        size: u64,
+    ) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Replaces the buffer's contents with `data`, growing it first if need
+    /// be. `data.len()` must be a multiple of `wgpu::COPY_BUFFER_ALIGNMENT`.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) {
+        let size = data.len() as u64;
+        if size > self.capacity {
+            let mut capacity = self.capacity;
+            while capacity < size {
+                capacity *= 2;
+            }
+            log::info!(
+                "growing {} from {} to {} bytes",
+                self.label,
+                self.capacity,
+                capacity
+            );
+
+            self.buffer = Self::allocate(device, self.label, self.usage, capacity);
+            self.capacity = capacity;
+        }
+
+        if !data.is_empty() {
+            queue.write_buffer(&self.buffer, 0, data);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::config::Config;
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The shader in the source tree, so edits show up without a rebuild.
+pub const SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/shader.wgsl");
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Notices when a file's modification time changes.
+struct FileWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl FileWatcher {
+    fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let modified = Self::modified(&path);
+        Self { path, modified }
+    }
+
+    fn modified(path: &PathBuf) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// The file's contents if it changed since the last poll. An empty file
+    /// is most likely an editor halfway through saving, so it's skipped until
+    /// the next change.
+    fn poll(&mut self) -> Option<String> {
+        let modified = Self::modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+
+        match std::fs::read_to_string(&self.path) {
+            Ok(source) if source.trim().is_empty() => None,
+            Ok(source) => {
+                self.modified = modified;
+                Some(source)
+            }
+            Err(e) => {
+                log::error!("couldn't read {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+pub enum Reload {
+    Config(Config),
+    Shader(String),
+}
+
+/// Polls the config file and shader for edits during development.
+pub struct HotReload {
+    config: Option<FileWatcher>,
+    shader: FileWatcher,
+    last_poll: Instant,
+}
+
+impl HotReload {
+    pub fn new(config: Option<&str>) -> Self {
+        log::info!("watching {} for changes", SHADER_PATH);
+        if let Some(path) = config {
+            log::info!("watching {} for changes", path);
+        }
+
+        Self {
+            config: config.map(FileWatcher::new),
+            shader: FileWatcher::new(SHADER_PATH),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Whatever changed since the last poll. Files are checked at most every
+    /// `POLL_INTERVAL`; a config that fails to parse is logged and skipped.
+    pub fn poll(&mut self) -> Vec<Reload> {
+        let mut reloads = Vec::new();
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return reloads;
+        }
+        self.last_poll = Instant::now();
+
+        if let Some(watcher) = &mut self.config {
+            if let Some(source) = watcher.poll() {
+                match Config::parse(&source) {
+                    Ok(config) => reloads.push(Reload::Config(config)),
+                    Err(e) => log::error!(
+                        "{}: {:#}; keeping the old config",
+                        watcher.path.display(),
+                        e
+                    ),
+                }
+            }
+        }
+        if let Some(source) = self.shader.poll() {
+            reloads.push(Reload::Shader(source));
+        }
+
+        reloads
+    }
+}
@@ -1,13 +1,38 @@
 mod app;
-mod constants;
-mod game;
-mod game_object;
-mod sprite;
+pub mod arena;
+pub mod batch;
+pub mod behaviour;
+pub mod camera;
+pub mod collision;
+pub mod config;
+pub mod constants;
+pub mod detonation;
+pub mod env;
+pub mod event;
+pub mod flocking;
+pub mod game;
+pub mod game_object;
+mod gpu_buffer;
+#[cfg(not(target_arch = "wasm32"))]
+mod hot_reload;
+pub mod mesh;
+pub mod mode;
+mod options;
+pub mod replay;
+pub mod spatial;
+pub mod sprite;
+pub mod state;
+pub mod stats;
 mod texture;
+pub mod timestep;
 mod utils;
+pub mod waves;
 
 use app::App;
-use constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use config::Config;
+use constants::{MAX_TICKS_PER_FRAME, TICK_RATE};
+use options::Options;
+use timestep::FixedTimestep;
 use utils::get_time;
 
 #[cfg(target_arch = "wasm32")]
@@ -33,10 +58,19 @@ pub async fn run() {
         }
     }
 
+    let options = Options::from_args();
+    let config = match &options.config {
+        Some(source) => Config::load(source).await.unwrap_or_else(|e| {
+            log::error!("couldn't load config, using defaults: {:#}", e);
+            Config::default()
+        }),
+        None => Config::default(),
+    };
+
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
         .with_title("passive")
-        .with_inner_size(LogicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT))
+        .with_inner_size(LogicalSize::new(config.window_width, config.window_height))
         .build(&event_loop)
         .unwrap();
 
@@ -47,8 +81,8 @@ pub async fn run() {
         use winit::dpi::PhysicalSize;
 
         let dpi = device_pixel_ratio();
-        let physical_width = (WINDOW_WIDTH as f64 * dpi) as u32;
-        let physical_height = (WINDOW_HEIGHT as f64 * dpi) as u32;
+        let physical_width = (config.window_width as f64 * dpi) as u32;
+        let physical_height = (config.window_height as f64 * dpi) as u32;
 
         window.set_inner_size(PhysicalSize::new(physical_width, physical_height));
 
@@ -64,41 +98,42 @@ pub async fn run() {
             .expect("Couldn't append canvas to document body.");
     }
 
-    let mut app = App::new(window).await;
+    let mut app = App::new(window, config, &options).await;
     let mut last_frame = get_time();
-    let mut dt = 0f32;
+    let mut timestep = FixedTimestep::new(1.0 / TICK_RATE, MAX_TICKS_PER_FRAME);
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent {
             ref event,
             window_id,
-        } if window_id == app.window().id() => {
-            if !app.input(event) {
-                match event {
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-
-                    WindowEvent::Resized(physical_size) => {
-                        app.resize(*physical_size);
-                    }
+        } if window_id == app.window().id() && !app.input(event) => match event {
+            WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
 
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        // new_inner_size is &&mut so we gotta deref twice
-                        app.resize(**new_inner_size);
-                    }
+            WindowEvent::Resized(physical_size) => {
+                app.resize(*physical_size);
+            }
 
-                    _ => {}
-                }
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                // new_inner_size is &&mut so we gotta deref twice
+                app.resize(**new_inner_size);
             }
-        }
+
+            _ => {}
+        },
 
         Event::RedrawRequested(window_id) if window_id == app.window().id() => {
             let current_frame = get_time();
-            dt = current_frame - last_frame;
+            let frame_time = current_frame - last_frame;
             last_frame = current_frame;
 
-            app.update(dt);
+            #[cfg(not(target_arch = "wasm32"))]
+            app.hot_reload();
+
+            for _ in 0..timestep.advance(frame_time) {
+                app.update(timestep.dt());
+            }
 
-            match app.render() {
+            match app.render(timestep.alpha()) {
                 Ok(_) => {}
                 // Reconfigure the surface if it's lost or outdated
                 Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
@@ -0,0 +1,95 @@
+use crate::config::Config;
+use crate::game_object::{Enemy, EnemyKind, Gate, Player};
+use crate::sprite::{Sprite, Vertex};
+
+use std::collections::HashMap;
+use std::ops::Range;
+use wgpu::util::DeviceExt;
+
+/// Names a piece of geometry in the `MeshRegistry`. Meshes are drawn in the
+/// order they're declared here, later ones on top.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MeshId {
+    Player,
+    Enemy(EnemyKind),
+    Gate,
+}
+
+/// Geometry in the sprite's local space, centred on its origin.
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+}
+
+/// Where a mesh lives in the registry's buffers.
+#[derive(Clone, Debug)]
+pub struct MeshRange {
+    pub base_vertex: i32,
+    pub indices: Range<u32>,
+}
+
+/// Every sprite mesh, packed into one vertex and one index buffer that are
+/// uploaded once rather than every frame.
+pub struct MeshRegistry {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    ranges: HashMap<MeshId, MeshRange>,
+}
+
+impl MeshRegistry {
+    /// Builds and uploads the meshes of every `Sprite` type. Sizes come from
+    /// `config`, so make a new registry when it changes.
+    pub fn new(device: &wgpu::Device, config: &Config) -> Self {
+        let meshes = [
+            Player::meshes(config),
+            Enemy::meshes(config),
+            Gate::meshes(config),
+        ];
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut ranges = HashMap::new();
+        for (id, mesh) in meshes.into_iter().flatten() {
+            let first_index = indices.len() as u32;
+            ranges.insert(
+                id,
+                MeshRange {
+                    base_vertex: vertices.len() as i32,
+                    indices: first_index..first_index + mesh.indices.len() as u32,
+                },
+            );
+            vertices.extend(mesh.vertices);
+            indices.extend(mesh.indices);
+        }
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Self {
+            vertex_buffer,
+            index_buffer,
+            ranges,
+        }
+    }
+
+    pub fn vertex_buffer(&self) -> &wgpu::Buffer {
+        &self.vertex_buffer
+    }
+
+    pub fn index_buffer(&self) -> &wgpu::Buffer {
+        &self.index_buffer
+    }
+
+    pub fn get(&self, id: MeshId) -> &MeshRange {
+        &self.ranges[&id]
+    }
+}
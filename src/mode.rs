@@ -0,0 +1,47 @@
+use crate::arena::Arena;
+use crate::constants::{VIEW_HEIGHT, VIEW_WIDTH};
+use crate::waves::WaveSchedule;
+
+/// Everything that differs between ways of playing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameMode {
+    pub name: String,
+    pub arena: Arena,
+    pub waves: WaveSchedule,
+}
+
+impl GameMode {
+    /// The original game: an arena exactly filling the default view.
+    pub fn classic() -> Self {
+        Self {
+            name: "classic".to_string(),
+            arena: Arena::new(VIEW_WIDTH, VIEW_HEIGHT),
+            waves: WaveSchedule::parse(include_str!("../assets/waves/classic.toml"))
+                .expect("built-in classic waves"),
+        }
+    }
+
+    /// Twice the size in each direction; the camera follows the player.
+    pub fn sprawl() -> Self {
+        Self {
+            name: "sprawl".to_string(),
+            arena: Arena::new(2.0 * VIEW_WIDTH, 2.0 * VIEW_HEIGHT),
+            waves: WaveSchedule::parse(include_str!("../assets/waves/sprawl.toml"))
+                .expect("built-in sprawl waves"),
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::classic()),
+            "sprawl" => Some(Self::sprawl()),
+            _ => None,
+        }
+    }
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
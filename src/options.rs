@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+/// Command line options for the windowed game.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Play every run from this seed instead of a random one.
+    pub seed: Option<u64>,
+    /// Name of the `GameMode` to play.
+    pub mode: Option<String>,
+    /// Wave file to use instead of the mode's built-in one.
+    pub waves: Option<PathBuf>,
+    /// Directory to write a replay of every run into.
+    pub record: Option<PathBuf>,
+    /// Config file to load tuning values from; a URL on the web.
+    pub config: Option<String>,
+    /// Reload the config file and shader when they change on disk. Config
+    /// changes made while a replay is being recorded wait for it to end.
+    pub watch: bool,
+}
+
+impl Options {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_args() -> Self {
+        let mut options = Self::default();
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => match args.next().map(|s| s.parse()) {
+                    Some(Ok(seed)) => options.seed = Some(seed),
+                    _ => log::warn!("--seed expects an unsigned integer"),
+                },
+                "--mode" => match args.next() {
+                    Some(mode) => options.mode = Some(mode),
+                    None => log::warn!("--mode expects a mode name"),
+                },
+                "--waves" => match args.next() {
+                    Some(path) => options.waves = Some(path.into()),
+                    None => log::warn!("--waves expects a file"),
+                },
+                "--record" => match args.next() {
+                    Some(dir) => options.record = Some(dir.into()),
+                    None => log::warn!("--record expects a directory"),
+                },
+                "--config" => match args.next() {
+                    Some(path) => options.config = Some(path),
+                    None => log::warn!("--config expects a file"),
+                },
+                "--watch" => options.watch = true,
+                _ => log::warn!("ignoring unknown argument {}", arg),
+            }
+        }
+
+        options
+    }
+
+    /// Reads `seed`, `mode` and `config` from the page's query string.
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_args() -> Self {
+        let mut options = Self::default();
+        let params = web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .and_then(|search| web_sys::UrlSearchParams::new_with_str(&search).ok());
+
+        if let Some(params) = params {
+            if let Some(seed) = params.get("seed") {
+                match seed.parse() {
+                    Ok(seed) => options.seed = Some(seed),
+                    Err(_) => log::warn!("seed expects an unsigned integer"),
+                }
+            }
+            options.mode = params.get("mode");
+            options.config = params.get("config");
+        }
+
+        options
+    }
+}
@@ -0,0 +1,319 @@
+use crate::config::Config;
+use crate::game::Game;
+use crate::mode::GameMode;
+use crate::waves::WaveSchedule;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use winit::event::VirtualKeyCode;
+
+/// First line of a replay file: everything needed to rebuild the run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Header {
+    pub version: String,
+    #[serde(default = "default_mode")]
+    pub mode: String,
+    pub seed: u64,
+    pub dt: f32,
+    /// The wave schedule played, in case it wasn't the mode's built-in one.
+    #[serde(default)]
+    pub waves: Option<WaveSchedule>,
+    /// Tuning values in effect; older replays used the defaults.
+    #[serde(default)]
+    pub config: Option<Config>,
+}
+
+fn default_mode() -> String {
+    GameMode::default().name
+}
+
+/// One simulated tick: the keys held during it and a hash of the game state
+/// once it finished.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tick {
+    #[serde(rename = "k")]
+    pub keys: Vec<VirtualKeyCode>,
+    #[serde(rename = "h")]
+    pub hash: u64,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse {
+        line: usize,
+        error: serde_json::Error,
+    },
+    MissingHeader,
+    UnknownMode(String),
+    /// Playback diverged from the recording at `tick` (0-based).
+    Desync {
+        tick: usize,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "couldn't read replay: {}", error),
+            ReplayError::Parse { line, error } => write!(f, "line {}: {}", line, error),
+            ReplayError::MissingHeader => write!(f, "replay has no header"),
+            ReplayError::UnknownMode(mode) => write!(f, "replay uses unknown mode {:?}", mode),
+            ReplayError::Desync {
+                tick,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "desync at tick {}: expected state {:016x}, got {:016x}",
+                tick, expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        ReplayError::Io(error)
+    }
+}
+
+/// FNV-1a over everything that should match between a recording and its
+/// playback: timer, stats and the position of every entity.
+pub fn state_hash(game: &Game) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    let mut feed = |bits: u64| {
+        for byte in bits.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+
+    let stats = game.stats();
+    feed(stats.time.to_bits() as u64);
+    feed(stats.score);
+    feed(stats.multiplier as u64);
+    feed(stats.lives as u64);
+
+    let (x, y) = game.player.game_object.coords;
+    feed(x.to_bits() as u64);
+    feed(y.to_bits() as u64);
+
+    feed(game.enemies.len() as u64);
+    for enemy in game.enemies.iter() {
+        let (x, y) = enemy.game_object.coords;
+        feed(x.to_bits() as u64);
+        feed(y.to_bits() as u64);
+    }
+
+    feed(game.gates.len() as u64);
+    for gate in game.gates.iter() {
+        let (x, y) = gate.game_object.coords;
+        feed(x.to_bits() as u64);
+        feed(y.to_bits() as u64);
+        feed(gate.rotation.to_bits() as u64);
+    }
+
+    hash
+}
+
+/// Writes one run to a JSON-lines replay as it is played.
+pub struct Recorder<W: Write> {
+    writer: W,
+    ticks: usize,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut writer: W, game: &Game, dt: f32) -> io::Result<Self> {
+        let header = Header {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            mode: game.mode().name.clone(),
+            seed: game.seed(),
+            dt,
+            waves: Some(game.mode().waves.clone()),
+            config: Some(game.config().clone()),
+        };
+        serde_json::to_writer(&mut writer, &header)?;
+        writeln!(writer)?;
+
+        Ok(Self { writer, ticks: 0 })
+    }
+
+    /// Call after every `Game::update` that advanced the simulation.
+    pub fn record(&mut self, game: &Game) -> io::Result<()> {
+        let tick = Tick {
+            keys: game.keys.iter().copied().collect(),
+            hash: state_hash(game),
+        };
+        serde_json::to_writer(&mut self.writer, &tick)?;
+        writeln!(self.writer)?;
+        self.ticks += 1;
+
+        Ok(())
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+pub struct Replay {
+    pub header: Header,
+    pub ticks: Vec<Tick>,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read(reader: impl BufRead) -> Result<Self, ReplayError> {
+        let mut lines = reader.lines().enumerate();
+
+        let header: Header = match lines.next() {
+            Some((_, line)) => serde_json::from_str(&line?)
+                .map_err(|error| ReplayError::Parse { line: 1, error })?,
+            None => return Err(ReplayError::MissingHeader),
+        };
+
+        let mut ticks = Vec::new();
+        for (i, line) in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            ticks.push(
+                serde_json::from_str(&line)
+                    .map_err(|error| ReplayError::Parse { line: i + 1, error })?,
+            );
+        }
+
+        Ok(Self { header, ticks })
+    }
+
+    /// Re-runs the recording headlessly, checking the state after every tick
+    /// against the recorded hash. Returns the game as it was at the end.
+    pub fn play(&self) -> Result<Game, ReplayError> {
+        if self.header.version != env!("CARGO_PKG_VERSION") {
+            log::warn!(
+                "replay was recorded with version {}, playing back with {}",
+                self.header.version,
+                env!("CARGO_PKG_VERSION")
+            );
+        }
+
+        let mut mode = GameMode::by_name(&self.header.mode)
+            .ok_or_else(|| ReplayError::UnknownMode(self.header.mode.clone()))?;
+        if let Some(waves) = &self.header.waves {
+            mode.waves = waves.clone();
+        }
+        let config = self.header.config.clone().unwrap_or_default();
+        let mut game = Game::with_config(config, mode, self.header.seed);
+        game.start();
+
+        for (i, tick) in self.ticks.iter().enumerate() {
+            game.keys = tick.keys.iter().copied().collect();
+            game.update(self.header.dt);
+
+            let actual = state_hash(&game);
+            if actual != tick.hash {
+                return Err(ReplayError::Desync {
+                    tick: i,
+                    expected: tick.hash,
+                    actual,
+                });
+            }
+        }
+
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TICK_RATE;
+
+    const TICKS: usize = 1200;
+
+    /// Records a run of `mode` from `seed`, steering in a square until the
+    /// game ends or `TICKS` have passed, like `App` does.
+    fn record(mode: GameMode, seed: u64) -> Vec<u8> {
+        let dt = 1.0 / TICK_RATE;
+        let mut game = Game::with_config(Config::default(), mode, seed);
+        game.start();
+
+        let mut recorder = Recorder::new(Vec::new(), &game, dt).unwrap();
+        let square = [
+            VirtualKeyCode::W,
+            VirtualKeyCode::D,
+            VirtualKeyCode::S,
+            VirtualKeyCode::A,
+        ];
+        for i in 0..TICKS {
+            if !game.state().simulates() {
+                break;
+            }
+            game.keys = [square[i / 40 % square.len()]].into_iter().collect();
+            game.update(dt);
+            recorder.record(&game).unwrap();
+        }
+
+        recorder.finish().unwrap()
+    }
+
+    #[test]
+    fn recording_plays_back() {
+        for mode in [GameMode::classic(), GameMode::sprawl()] {
+            for seed in 0..5 {
+                let recording = record(mode.clone(), seed);
+                let replay = Replay::read(recording.as_slice()).unwrap();
+                assert_eq!(replay.header.seed, seed);
+                assert_eq!(replay.header.mode, mode.name);
+
+                let game = replay.play().unwrap();
+                assert_eq!(
+                    state_hash(&game),
+                    replay.ticks.last().unwrap().hash,
+                    "{} seed {}",
+                    mode.name,
+                    seed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted_tick_reports_desync_there() {
+        let recording = record(GameMode::sprawl(), 7);
+        let mut replay = Replay::read(recording.as_slice()).unwrap();
+        let corrupted = replay.ticks.len() / 2;
+        replay.ticks[corrupted].hash ^= 1;
+
+        match replay.play() {
+            Err(ReplayError::Desync { tick, .. }) => assert_eq!(tick, corrupted),
+            Err(error) => panic!("expected a desync, got {}", error),
+            Ok(_) => panic!("expected a desync, playback succeeded"),
+        }
+    }
+
+    #[test]
+    fn missing_header() {
+        assert!(matches!(
+            Replay::read(&b""[..]),
+            Err(ReplayError::MissingHeader)
+        ));
+    }
+}
@@ -0,0 +1,272 @@
+use crate::arena::Arena;
+
+/// Grids never get more cells than this; past it the cells grow instead.
+const MAX_CELLS: usize = 1 << 16;
+
+/// A uniform grid over the arena for finding nearby points without testing
+/// every pair. Rebuilt from scratch whenever the points move, which is a
+/// linear counting sort, so it's cheap to do every tick.
+///
+/// Points are identified by their index in the iterator given to `rebuild`.
+/// Points outside the arena are filed in the nearest edge cell.
+#[derive(Clone, Debug, Default)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    origin: (f32, f32),
+    cols: usize,
+    rows: usize,
+    /// `entries[cell_start[c]..cell_start[c + 1]]` are the points in cell `c`.
+    cell_start: Vec<u32>,
+    entries: Vec<u32>,
+    points: Vec<(f32, f32)>,
+    cells: Vec<u32>,
+}
+
+impl SpatialGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Position of point `i` as of the last rebuild.
+    pub fn point(&self, i: usize) -> (f32, f32) {
+        self.points[i]
+    }
+
+    /// Files `points` into cells of roughly `cell_size`. Queries are fastest
+    /// when `cell_size` is about the radius they're usually made with.
+    pub fn rebuild(
+        &mut self,
+        arena: &Arena,
+        cell_size: f32,
+        points: impl IntoIterator<Item = (f32, f32)>,
+    ) {
+        let mut cell_size = cell_size.max(f32::EPSILON);
+        while (arena.width() / cell_size).ceil() * (arena.height() / cell_size).ceil()
+            > MAX_CELLS as f32
+        {
+            cell_size *= 2.0;
+        }
+
+        self.cell_size = cell_size;
+        self.origin = (-arena.half_width, -arena.half_height);
+        self.cols = ((arena.width() / cell_size).ceil() as usize).max(1);
+        self.rows = ((arena.height() / cell_size).ceil() as usize).max(1);
+
+        self.points.clear();
+        self.points.extend(points);
+
+        self.cells.clear();
+        self.cell_start.clear();
+        self.cell_start.resize(self.cols * self.rows + 1, 0);
+        for i in 0..self.points.len() {
+            let (col, row) = self.cell_of(self.points[i]);
+            let cell = (row * self.cols + col) as u32;
+            self.cells.push(cell);
+            self.cell_start[cell as usize + 1] += 1;
+        }
+        for c in 1..self.cell_start.len() {
+            self.cell_start[c] += self.cell_start[c - 1];
+        }
+
+        // stable, so each cell lists its points in index order
+        self.entries.clear();
+        self.entries.resize(self.points.len(), 0);
+        let mut next = self.cell_start.clone();
+        for (i, &cell) in self.cells.iter().enumerate() {
+            self.entries[next[cell as usize] as usize] = i as u32;
+            next[cell as usize] += 1;
+        }
+    }
+
+    fn cell_of(&self, (x, y): (f32, f32)) -> (usize, usize) {
+        let col = ((x - self.origin.0) / self.cell_size).floor();
+        let row = ((y - self.origin.1) / self.cell_size).floor();
+        (
+            (col.max(0.0) as usize).min(self.cols - 1),
+            (row.max(0.0) as usize).min(self.rows - 1),
+        )
+    }
+
+    /// Calls `f` with every point in cells overlapping the box from `min` to
+    /// `max`, cell by cell.
+    fn for_each_in_box(&self, min: (f32, f32), max: (f32, f32), mut f: impl FnMut(usize)) {
+        if self.points.is_empty() {
+            return;
+        }
+
+        let (col0, row0) = self.cell_of(min);
+        let (col1, row1) = self.cell_of(max);
+        for row in row0..=row1 {
+            for col in col0..=col1 {
+                let cell = row * self.cols + col;
+                let start = self.cell_start[cell] as usize;
+                let end = self.cell_start[cell + 1] as usize;
+                for &i in &self.entries[start..end] {
+                    f(i as usize);
+                }
+            }
+        }
+    }
+
+    /// Calls `f` with the index of every point within `radius` of `center`.
+    pub fn for_each_within(&self, center: (f32, f32), radius: f32, mut f: impl FnMut(usize)) {
+        let radius_sq = radius * radius;
+        self.for_each_in_box(
+            (center.0 - radius, center.1 - radius),
+            (center.0 + radius, center.1 + radius),
+            |i| {
+                let (dx, dy) = (self.points[i].0 - center.0, self.points[i].1 - center.1);
+                if dx * dx + dy * dy <= radius_sq {
+                    f(i);
+                }
+            },
+        );
+    }
+
+    /// Calls `f` with the index of every point within `radius` of the segment
+    /// from `a` to `b`.
+    pub fn for_each_near_segment(
+        &self,
+        a: (f32, f32),
+        b: (f32, f32),
+        radius: f32,
+        mut f: impl FnMut(usize),
+    ) {
+        let radius_sq = radius * radius;
+        let (abx, aby) = (b.0 - a.0, b.1 - a.1);
+        let length_sq = abx * abx + aby * aby;
+
+        self.for_each_in_box(
+            (a.0.min(b.0) - radius, a.1.min(b.1) - radius),
+            (a.0.max(b.0) + radius, a.1.max(b.1) + radius),
+            |i| {
+                let p = self.points[i];
+                let t = if length_sq > 0.0 {
+                    (((p.0 - a.0) * abx + (p.1 - a.1) * aby) / length_sq).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let (dx, dy) = (p.0 - (a.0 + t * abx), p.1 - (a.1 + t * aby));
+                if dx * dx + dy * dy <= radius_sq {
+                    f(i);
+                }
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const CELL_SIZE: f32 = 0.25;
+
+    /// Points scattered over and a little beyond a 4 x 2 arena, plus some
+    /// sitting exactly on cell boundaries.
+    fn points(rng: &mut StdRng) -> Vec<(f32, f32)> {
+        let mut points = (0..2000)
+            .map(|_| (rng.gen_range(-2.5..2.5), rng.gen_range(-1.5..1.5)))
+            .collect::<Vec<_>>();
+        for i in -8..=8 {
+            points.push((i as f32 * CELL_SIZE, 0.0));
+            points.push((0.0, (i / 2) as f32 * CELL_SIZE));
+        }
+        points
+    }
+
+    fn query(rng: &mut StdRng) -> ((f32, f32), f32) {
+        let center = (rng.gen_range(-2.5..2.5), rng.gen_range(-1.5..1.5));
+        let radius = match rng.gen_range(0..3) {
+            0 => rng.gen_range(0.0..0.6),
+            // exactly a whole number of cells
+            1 => rng.gen_range(0..4) as f32 * CELL_SIZE,
+            _ => rng.gen_range(0.0..3.0),
+        };
+        (center, radius)
+    }
+
+    fn grid(points: &[(f32, f32)]) -> SpatialGrid {
+        let mut grid = SpatialGrid::new();
+        grid.rebuild(&Arena::new(4.0, 2.0), CELL_SIZE, points.iter().copied());
+        grid
+    }
+
+    #[test]
+    fn within_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let points = points(&mut rng);
+        let grid = grid(&points);
+
+        for _ in 0..500 {
+            let (center, radius) = query(&mut rng);
+
+            let mut found = Vec::new();
+            grid.for_each_within(center, radius, |i| found.push(i));
+            found.sort_unstable();
+
+            let expected = (0..points.len())
+                .filter(|&i| {
+                    let (dx, dy) = (points[i].0 - center.0, points[i].1 - center.1);
+                    dx * dx + dy * dy <= radius * radius
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected, "within {} of {:?}", radius, center);
+        }
+    }
+
+    #[test]
+    fn near_segment_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let points = points(&mut rng);
+        let grid = grid(&points);
+
+        for _ in 0..500 {
+            let (a, radius) = query(&mut rng);
+            let b = if rng.gen_bool(0.1) {
+                a
+            } else {
+                (rng.gen_range(-2.5..2.5), rng.gen_range(-1.5..1.5))
+            };
+
+            let mut found = Vec::new();
+            grid.for_each_near_segment(a, b, radius, |i| found.push(i));
+            found.sort_unstable();
+
+            let (abx, aby) = (b.0 - a.0, b.1 - a.1);
+            let length_sq = abx * abx + aby * aby;
+            let expected = (0..points.len())
+                .filter(|&i| {
+                    let p = points[i];
+                    let t = if length_sq > 0.0 {
+                        (((p.0 - a.0) * abx + (p.1 - a.1) * aby) / length_sq).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                    let (dx, dy) = (p.0 - (a.0 + t * abx), p.1 - (a.1 + t * aby));
+                    dx * dx + dy * dy <= radius * radius
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected, "within {} of {:?}-{:?}", radius, a, b);
+        }
+    }
+
+    #[test]
+    fn cells_list_points_in_index_order() {
+        let points = [(0.1, 0.1), (-1.0, 0.5), (0.2, 0.1), (0.1, 0.2)];
+        let grid = grid(&points);
+
+        let mut found = Vec::new();
+        grid.for_each_within((0.1, 0.1), 0.1, |i| found.push(i));
+        assert_eq!(found, [0, 2, 3]);
+    }
+
+    #[test]
+    fn empty_grid() {
+        let grid = grid(&[]);
+        grid.for_each_within((0.0, 0.0), 10.0, |_| panic!("no points"));
+        SpatialGrid::new()
+            .for_each_near_segment((0.0, 0.0), (1.0, 1.0), 1.0, |_| panic!("no points"));
+    }
+}
@@ -1,3 +1,6 @@
+use crate::config::Config;
+use crate::mesh::{Mesh, MeshId};
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
@@ -32,9 +35,26 @@ impl Vertex {
 pub struct Instance {
     pub instance_pos: [f32; 3],
     pub theta: f32, // Rotation angle
+    /// Stretch along the mesh's own x and y axes, applied before rotation.
+    pub scale: [f32; 2],
+    /// RGBA multiplied into the sampled texture colour.
+    pub tint: [f32; 4],
+    pub opacity: f32,
 }
 
 impl Instance {
+    /// An instance at `(x, y)` turned by `theta`, at its mesh's own size and
+    /// colours.
+    pub fn new(x: f32, y: f32, theta: f32) -> Self {
+        Self {
+            instance_pos: [x, y, 0.0],
+            theta,
+            scale: [1.0, 1.0],
+            tint: [1.0; 4],
+            opacity: 1.0,
+        }
+    }
+
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         use std::mem;
         wgpu::VertexBufferLayout {
@@ -46,12 +66,25 @@ impl Instance {
                     shader_location: 2,
                     format: wgpu::VertexFormat::Float32x3,
                 },
-                {
-                    wgpu::VertexAttribute {
-                        offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                        shader_location: 3,
-                        format: wgpu::VertexFormat::Float32,
-                    }
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 10]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32,
                 },
             ],
         }
@@ -61,11 +94,17 @@ impl Instance {
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Uniforms {
-    pub aspect_ratio: f32,
+    pub view_proj: [[f32; 4]; 4],
 }
 
 pub trait Sprite {
-    fn get_vertices() -> Vec<Vertex>;
-    fn get_indices() -> &'static [u16];
-    fn get_instance(&self) -> Instance;
+    /// Geometry for every mesh this type draws with. `MeshRegistry` uploads
+    /// these once, and again only when the config changes.
+    fn meshes(config: &Config) -> Vec<(MeshId, Mesh)>
+    where
+        Self: Sized;
+    fn mesh(&self) -> MeshId;
+    /// `alpha` is how far between the previous and current simulation tick
+    /// the frame being drawn lies.
+    fn get_instance(&self, alpha: f32, config: &Config) -> Instance;
 }
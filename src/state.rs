@@ -0,0 +1,33 @@
+use winit::event::VirtualKeyCode;
+
+/// Where the game is in its lifecycle: Title → Playing ⇄ Paused, and
+/// Playing → GameOver → Title once the player runs out of lives.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameState {
+    Title,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+impl GameState {
+    /// Whether `Game::update` advances the simulation in this state.
+    pub fn simulates(self) -> bool {
+        matches!(self, GameState::Playing)
+    }
+
+    /// The state a key press moves to, or `None` if this state ignores the
+    /// key. Movement keys are always tracked in `Game::keys` regardless.
+    pub fn on_key(self, key: VirtualKeyCode) -> Option<GameState> {
+        use VirtualKeyCode::{Escape, Return, Space};
+
+        match (self, key) {
+            (GameState::Title, Space | Return) => Some(GameState::Playing),
+            (GameState::Playing, Space | Escape) => Some(GameState::Paused),
+            (GameState::Paused, Space) => Some(GameState::Playing),
+            (GameState::Paused, Escape) => Some(GameState::Title),
+            (GameState::GameOver, Space | Return) => Some(GameState::Title),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// A read-only snapshot of the current run, cheap enough to take every frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameStats {
+    pub score: u64,
+    /// Applied to every kill; grows with each gate detonation and drops back
+    /// to 1 when the player dies.
+    pub multiplier: u32,
+    pub lives: u32,
+    pub kills: u32,
+    pub gates_detonated: u32,
+    /// Seconds of simulated play.
+    pub time: f32,
+    /// Whether the player is still protected after respawning.
+    pub invulnerable: bool,
+}
@@ -2,6 +2,7 @@ use anyhow::*;
 use image::{GenericImage, RgbaImage};
 
 pub struct Texture {
+    #[allow(dead_code)]
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
@@ -0,0 +1,44 @@
+/// Accumulates real frame time and hands it out in fixed-size simulation
+/// ticks, so the game advances identically regardless of frame rate.
+pub struct FixedTimestep {
+    dt: f32,
+    max_ticks: u32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(dt: f32, max_ticks: u32) -> Self {
+        Self {
+            dt,
+            max_ticks,
+            accumulator: 0f32,
+        }
+    }
+
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+
+    /// Adds `frame_time` seconds and returns how many ticks to run. If more
+    /// than `max_ticks` are owed (e.g. after a stall) the backlog is dropped
+    /// rather than spiralling into ever longer catch-up frames.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.max(0.0);
+
+        let owed = (self.accumulator / self.dt) as u32;
+        let ticks = owed.min(self.max_ticks);
+        self.accumulator -= ticks as f32 * self.dt;
+
+        if owed > self.max_ticks {
+            log::warn!("dropping {} simulation ticks", owed - self.max_ticks);
+            self.accumulator %= self.dt;
+        }
+
+        ticks
+    }
+
+    /// How far between the last two ticks the current frame lies, in [0, 1).
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.dt).clamp(0.0, 1.0)
+    }
+}